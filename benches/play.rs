@@ -0,0 +1,252 @@
+//! `cargo bench --no-default-features`
+//!
+//! The `baseline` benches run the code the game had before bitboards, on the
+//! board as it kept it then: a byte per square and a map of cells per brick.
+//! `turn_baseline` is the slide of the old `Game::play` and the old
+//! `Game::clear_lines`; `full_lines_baseline` is the line check of the
+//! latter. They are compared with the engine as it plays now, which pays for
+//! more than they do: [`Game::step`] also brings in tray bricks, deals and
+//! checks every direction for the status, and `full_lines_bitboard` builds
+//! its board from the grid first. `deal` is what dealing alone costs a turn.
+#![feature(test)]
+extern crate test;
+
+use avalanche_tetris::engine::*;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use strum::IntoEnumIterator;
+use test::{black_box, Bencher};
+
+const SIZES: [(u8, u8); 3] = [(6, 6), (10, 10), (15, 15)];
+
+/// Games up to a few dozen moves in, on each of the [`SIZES`], stopped
+/// short of the move that would end them
+fn midgames() -> Vec<Game> {
+    SIZES
+        .iter()
+        .enumerate()
+        .map(|(seed, &(h, w))| {
+            let mut game = Game::from_config(GameConfig::new(h, w), seed as u64).unwrap();
+            for dir in Dir::iter().cycle().take(40) {
+                let mut next = game.clone();
+                if next.step(dir).is_err() || next.status().is_over() {
+                    break;
+                }
+                game = next;
+            }
+            game.set_history_limit(0);
+            game
+        })
+        .collect()
+}
+
+/// Boards about two thirds full, with a full row and column on each
+fn grids() -> Vec<(u8, u8, Vec<Sq>)> {
+    let mut rng = Pcg32::seed_from_u64(7);
+    SIZES
+        .iter()
+        .map(|&(h, w)| {
+            let mut grid: Vec<Sq> = (0..h as usize * w as usize)
+                .map(|_| match rng.gen_ratio(2, 3) {
+                    true => Sq::Dot(1),
+                    false => Sq::Empty,
+                })
+                .collect();
+            (0..w as usize).for_each(|c| grid[c] = Sq::Dot(1));
+            (0..h as usize).for_each(|r| grid[r * w as usize] = Sq::Dot(1));
+            (h, w, grid)
+        })
+        .collect()
+}
+
+/// The cells of the full rows and columns of a square board, found the way
+/// the old `clear_lines` did: a pass over the whole grid for each of them
+fn baseline_full_lines(w: usize, grid: &[u8]) -> Vec<usize> {
+    let mut cleared = vec![];
+    for idx in 0..w {
+        let h = grid.iter().enumerate().all(|(i, &x)| i / w != idx || x > 0);
+        let v = grid.iter().enumerate().all(|(i, &x)| i % w != idx || x > 0);
+        for i in 0..w {
+            if h {
+                cleared.push(idx * w + i);
+            }
+            if v {
+                cleared.push(idx + w * i);
+            }
+        }
+    }
+    cleared
+}
+
+/// A square board as the game kept it before bitboards
+#[derive(Clone)]
+struct Baseline {
+    width: usize,
+    grid: Vec<u8>,
+    bricks: Vec<Brick>,
+}
+
+impl Baseline {
+    fn new(game: &Game) -> Self {
+        Self {
+            width: game.width() as usize,
+            grid: game.iter().map(|sq| u8::from(!sq.is_empty())).collect(),
+            bricks: game.bricks().to_vec(),
+        }
+    }
+    /// The slide of the old `Game::play`: each pass lifts every brick off the
+    /// grid and probes the square past each of its dots
+    fn slide(&mut self, dir: Dir) {
+        let w = self.width;
+        let delta = |x| match dir {
+            Dir::Up => x + w,
+            Dir::Down => x - w,
+            Dir::Left => x - 1,
+            Dir::Right => x + 1,
+        };
+        let mut dirty = true;
+        let mut ids = Vec::new();
+        while dirty {
+            dirty = false;
+            for (i, b) in self.bricks.iter_mut().enumerate() {
+                if ids.contains(&i) {
+                    continue;
+                }
+                let at_edge = b.cells().any(|k| match dir {
+                    Dir::Up => k / w == w - 1,
+                    Dir::Down => k / w == 0,
+                    Dir::Left => k % w == 0,
+                    Dir::Right => k % w == w - 1,
+                });
+                if at_edge {
+                    ids.push(i);
+                    continue;
+                }
+                b.cells().for_each(|d| self.grid[d] = 0);
+                if !b.cells().any(|d| self.grid[delta(d)] > 0) {
+                    b.0 =
+                        b.0.drain()
+                            .map(|(k, v)| (delta(k as usize) as Cell, v))
+                            .collect();
+                    dirty = true;
+                    ids.push(i);
+                }
+                b.cells().for_each(|d| self.grid[d] = 1);
+            }
+        }
+    }
+    /// The old `Game::clear_lines`, splitting bricks the way the engine does
+    fn clear_lines(&mut self) -> Vec<usize> {
+        let cleared = baseline_full_lines(self.width, &self.grid);
+        if !cleared.is_empty() {
+            let (mut cut, mut next) = (vec![], BrickId::default());
+            for b in self.bricks.iter_mut() {
+                if b.contains_any(&cleared) {
+                    cut.append(&mut b.cut_at(&cleared, self.width as u8, &mut next));
+                }
+            }
+            self.bricks.retain(|b| !b.0.is_empty());
+            self.bricks.append(&mut cut);
+            for &ele in cleared.iter() {
+                self.grid[ele] = 0;
+            }
+        }
+        cleared
+    }
+}
+
+/// A turn each way from every midgame
+#[bench]
+fn turn(b: &mut Bencher) {
+    let games = midgames();
+    b.iter(|| {
+        for game in &games {
+            for dir in Dir::iter() {
+                black_box(game.clone().step(dir).ok());
+            }
+        }
+    });
+}
+
+/// [`turn`] with the old slide and line clear, converting each midgame to
+/// the old board once up front
+#[bench]
+fn turn_baseline(b: &mut Bencher) {
+    let boards: Vec<_> = midgames().iter().map(Baseline::new).collect();
+    b.iter(|| {
+        for board in &boards {
+            for dir in Dir::iter() {
+                let mut board = board.clone();
+                board.slide(dir);
+                black_box(board.clear_lines());
+            }
+        }
+    });
+}
+
+/// Dealing a brick into the trays of every midgame, as [`turn`] does
+#[bench]
+fn deal(b: &mut Bencher) {
+    let games = midgames();
+    b.iter(|| {
+        for game in &games {
+            for _ in Dir::iter() {
+                black_box(game.clone().gen_tray_brick().ok());
+            }
+        }
+    });
+}
+
+#[bench]
+fn productive(b: &mut Bencher) {
+    let games = midgames();
+    b.iter(|| {
+        for game in &games {
+            Dir::iter().for_each(|dir| {
+                black_box(game.is_productive(dir));
+            });
+        }
+    });
+}
+
+#[bench]
+fn full_lines_baseline(b: &mut Bencher) {
+    let grids: Vec<_> = grids()
+        .into_iter()
+        .map(|(_, w, grid)| {
+            let grid: Vec<u8> = grid.iter().map(|sq| u8::from(!sq.is_empty())).collect();
+            (w as usize, grid)
+        })
+        .collect();
+    b.iter(|| {
+        for (w, grid) in &grids {
+            black_box(baseline_full_lines(*w, grid));
+        }
+    });
+}
+
+/// Line checks with the board built from the grid each time
+#[bench]
+fn full_lines_bitboard(b: &mut Bencher) {
+    let grids = grids();
+    b.iter(|| {
+        for (h, w, grid) in &grids {
+            let board = Bitboard::from_grid(*w, *h, grid);
+            black_box((board.full_rows(), board.full_cols()));
+        }
+    });
+}
+
+/// Line checks on a board kept up to date, as [`Game`] keeps its own
+#[bench]
+fn full_lines_kept(b: &mut Bencher) {
+    let boards: Vec<_> = grids()
+        .into_iter()
+        .map(|(h, w, grid)| Bitboard::from_grid(w, h, &grid))
+        .collect();
+    b.iter(|| {
+        for board in &boards {
+            black_box((board.full_rows(), board.full_cols()));
+        }
+    });
+}
@@ -1,11 +1,13 @@
 use autodefault::autodefault;
 use std::collections::HashMap;
 
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
 use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
 use bevy::render::texture::DEFAULT_IMAGE_HANDLE;
-use strum::IntoEnumIterator;
+use bevy::utils::BoxedFuture;
 
-use super::shapes::Shape;
+use crate::engine::{Obstacle, ShapeSet};
 
 /// Material of a `Sprite` with a texture and color
 #[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
@@ -34,7 +36,7 @@ impl SpriteMaterial {
             image: self.texture.clone().into(),
         }
     }
-    pub fn _button(&self, style: Style) -> ButtonBundle {
+    pub fn button(&self, style: Style) -> ButtonBundle {
         ButtonBundle {
             style,
             color: self.color.into(),
@@ -61,7 +63,7 @@ pub struct BoardAssets {
     pub tray: SpriteMaterial,
     pub bg: SpriteMaterial,
     pub sq: SpriteMaterial,
-    pub brick: HashMap<Shape, SpriteMaterial>,
+    pub obstacle: HashMap<Obstacle, SpriteMaterial>,
     pub dot: Vec<SpriteMaterial>,
     pub font: Handle<Font>,
 }
@@ -80,6 +82,16 @@ impl FromWorld for BoardAssets {
                 texture: asset_server.load("sprites/red.png"),
                 color: Color::NONE,
             },
+            obstacle: [
+                (Obstacle::Wall, Color::DARK_GRAY),
+                (Obstacle::Rock, Color::GRAY),
+            ]
+            .into_iter()
+            .map(|(obstacle, color)| {
+                let texture = asset_server.load("sprites/obstacle.png");
+                (obstacle, SpriteMaterial { color, texture })
+            })
+            .collect(),
             font: asset_server.load("fonts/FiraMono-Medium.ttf"),
             dot: [
                 Color::LIME_GREEN,
@@ -95,23 +107,6 @@ impl FromWorld for BoardAssets {
                 texture: asset_server.load("sprites/red.png"),
             })
             .collect(),
-            brick: Shape::iter()
-                .zip(
-                    [
-                        Color::LIME_GREEN,
-                        Color::TEAL,
-                        Color::AQUAMARINE,
-                        Color::TOMATO,
-                        Color::MAROON,
-                        Color::PURPLE,
-                    ]
-                    .iter()
-                    .map(|&color| SpriteMaterial {
-                        color,
-                        texture: asset_server.load("sprites/red.png"),
-                    }),
-                )
-                .collect(),
         }
     }
 }
@@ -141,3 +136,40 @@ impl BoardAssets {
         }
     }
 }
+
+/// A [`ShapeSet`] read from a `.shapes.ron` or `.shapes.json` file
+#[derive(Debug, Clone, Deref, TypeUuid)]
+#[uuid = "6a7c4c35-2a60-4f0b-9a53-94d6b0f1c2e7"]
+pub struct ShapeSetAsset(pub ShapeSet);
+
+#[derive(Default)]
+pub struct ShapeSetLoader;
+impl AssetLoader for ShapeSetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let text = std::str::from_utf8(bytes)?;
+            let set = match load_context.path().extension().and_then(|e| e.to_str()) {
+                Some("json") => ShapeSet::from_json(text)?,
+                _ => ShapeSet::from_ron(text)?,
+            };
+            load_context.set_default_asset(LoadedAsset::new(ShapeSetAsset(set)));
+            Ok(())
+        })
+    }
+    fn extensions(&self) -> &[&str] {
+        &["shapes.ron", "shapes.json"]
+    }
+}
+
+/// The shape pack new games deal from. Must be used as a resource.
+pub struct ShapePack(pub Handle<ShapeSetAsset>);
+impl FromWorld for ShapePack {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(asset_server.load("shapes/classic.shapes.ron"))
+    }
+}
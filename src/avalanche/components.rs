@@ -1,4 +1,5 @@
 use {
+    crate::engine::{BrickId, Dir},
     bevy::prelude::*,
     duplicate::*,
     // enum_dispatch::enum_dispatch,
@@ -6,7 +7,7 @@ use {
 };
 
 // Single Tuples
-duplicate! {[component t; [Idx] [usize];]
+duplicate! {[component t; [Idx] [usize]; [HoldSq] [usize];]
 #[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
 #[derive(Component,Deref,DerefMut, Copy, Clone, Debug)]
 pub struct component(pub t);
@@ -16,3 +17,25 @@ impl Display for component {
         write!(f, "component: {v}")
     }
 }}
+
+/// The tray a square belongs to; board squares have none
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Component, Deref, DerefMut, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraySq(pub Dir);
+
+/// The brick whose dot a square is showing
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Component, Deref, DerefMut, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BrickTag(pub BrickId);
+
+/// On-screen buttons that walk the game history
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HistoryButton {
+    Undo,
+    Redo,
+}
+
+/// Text child of a square showing the face value of its dot
+#[derive(Component, Copy, Clone, Debug)]
+pub struct DotText;
@@ -1,15 +1,16 @@
 use {
+    crate::engine::{Game, MoveReport},
     assets::*,
     autodefault::autodefault,
     bevy::{ecs::schedule::StateData, prelude::*},
-    grid::*,
+    components::{HistoryButton, HoldSq},
     // menu::MenuPlugin,
     menu_plugin::MenuMaterials,
 };
 mod assets;
 mod components;
 mod grid;
-mod shapes;
+mod playback;
 mod systems;
 
 #[derive(Component)]
@@ -20,6 +21,40 @@ pub struct Board;
 #[component(storage = "SparseSet")]
 pub struct ScoreBoard;
 
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Preview;
+
+/// The tray showing the held brick: `side` squares of `size` pixels a side,
+/// enough for the longest shape of the game's set
+#[derive(Component)]
+pub struct HoldSlot {
+    pub side: usize,
+    pub size: f32,
+}
+
+impl HoldSlot {
+    #[autodefault]
+    pub fn style(&self) -> Style {
+        let side = Val::Px((self.size + 2.2) * self.side as f32);
+        Style {
+            size: Size::new(side, side),
+            flex_wrap: FlexWrap::Wrap,
+            flex_direction: FlexDirection::Row,
+        }
+    }
+    #[autodefault]
+    pub fn spawn_squares(&self, p: &mut ChildBuilder, assets: &BoardAssets) {
+        for i in 0..self.side * self.side {
+            p.spawn_bundle(assets.sq.node(Style {
+                size: Size::new(Val::Px(self.size), Val::Px(self.size)),
+                margin: UiRect::all(Val::Px(1.0)),
+            }))
+            .insert(HoldSq(i));
+        }
+    }
+}
+
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::InspectorPlugin;
 
@@ -38,11 +73,31 @@ impl<T: StateData + Copy> Plugin for AvalancheGamePlugin<T> {
             .add_plugin(bevy_tweening::TweeningPlugin)
             .add_system_set(SystemSet::on_enter(InGame).with_system(create_grid))
             .init_resource::<BoardAssets>()
+            .add_asset::<ShapeSetAsset>()
+            .init_asset_loader::<ShapeSetLoader>()
+            .init_resource::<ShapePack>()
+            .add_event::<MoveReport>()
             .add_system_set(
                 SystemSet::on_update(InGame)
                     // .with_system(systems::deck_complete.exclusive_system().at_end())
-                    .with_system(systems::spawn_shape)
-                    // .with_system(systems::move_bricks),
+                    .with_system(systems::move_bricks)
+                    .with_system(systems::undo_redo)
+                    .with_system(systems::pick_level.before(systems::spawn_shape))
+                    .with_system(systems::pick_shapes.before(systems::spawn_shape))
+                    .with_system(systems::apply_shape_pack.before(systems::spawn_shape))
+                    .with_system(systems::show_preview.after(systems::spawn_shape))
+                    .with_system(systems::resize_hold.after(systems::spawn_shape))
+                    .with_system(systems::show_hold.after(systems::resize_hold))
+                    .with_system(systems::tag_bricks.after(systems::spawn_shape))
+                    .with_system(playback::tick_recording.before(systems::move_bricks))
+                    .with_system(playback::replay_controls)
+                    .with_system(playback::playback.after(playback::replay_controls))
+                    .with_system(
+                        systems::spawn_shape
+                            .after(systems::move_bricks)
+                            .after(systems::undo_redo)
+                            .after(playback::playback),
+                    ),
             )
             // .add_system_set(
             //     SystemSet::on_in_stack_update(InGame)
@@ -63,6 +118,11 @@ impl<T: StateData + Copy> Plugin for AvalancheGamePlugin<T> {
             // .add_system_set(SystemSet::on_in_stack_update(**self).with_system(on_completion))
             // .add_system_set(SystemSet::on_exit(**self).with_system(splash_on))
             ;
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system_set(
+            SystemSet::on_update(InGame)
+                .with_system(systems::save_load.before(systems::spawn_shape)),
+        );
         #[cfg(feature = "debug")]
         {
             app
@@ -81,7 +141,8 @@ pub fn create_grid(
 ) {
     // let mut rng = rand::thread_rng();
     let grid = Game::init(7, 7);
-    let size = menu.size / (grid.height() + 2 * 4) as f32;
+    let side = grid.height().max(grid.width()) as f32 + 2. * grid.tray_depth() as f32;
+    let size = menu.size / side;
     cmd.spawn_bundle(assets.bg.node(Style {
         position_type: PositionType::Absolute,
         size: Size::new(Val::Percent(100.), Val::Percent(100.)),
@@ -107,6 +168,65 @@ pub fn create_grid(
     cmd.spawn_bundle(score_board)
         .insert(ScoreBoard)
         .insert(Name::new("ScoreBoard"));
+    let preview = TextBundle {
+        style: Style {
+            position: UiRect {
+                left: Val::Percent(77.),
+                bottom: Val::Percent(55.),
+            },
+        },
+        ..assets.write_text("")
+    };
+    cmd.spawn_bundle(preview)
+        .insert(Preview)
+        .insert(Name::new("Preview"));
+    cmd.spawn_bundle(assets.bg.node(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Percent(3.),
+            bottom: Val::Percent(77.),
+        },
+        flex_direction: FlexDirection::ColumnReverse,
+    }))
+    .insert(Name::new("History"))
+    .with_children(|p| {
+        for (button, label) in [
+            (HistoryButton::Undo, "Undo (U)"),
+            (HistoryButton::Redo, "Redo (R)"),
+        ] {
+            p.spawn_bundle(assets.tray.button(Style {
+                margin: UiRect::all(Val::Px(2.)),
+                padding: UiRect::all(Val::Px(4.)),
+                justify_content: JustifyContent::Center,
+            }))
+            .insert(button)
+            .insert(Name::new(label))
+            .with_children(|p| {
+                p.spawn_bundle(assets.write_text(label));
+            });
+        }
+    });
+    cmd.spawn_bundle(assets.bg.node(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Percent(3.),
+            bottom: Val::Percent(45.),
+        },
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::Center,
+    }))
+    .insert(Name::new("Hold"))
+    .with_children(|p| {
+        p.spawn_bundle(assets.write_text("Hold (Shift+Arrow)"));
+        let slot = HoldSlot {
+            side: grid.config().shapes.extent() as usize,
+            size,
+        };
+        p.spawn_bundle(assets.tray.node(slot.style()))
+            .with_children(|p| slot.spawn_squares(p, &assets))
+            .insert(slot);
+    });
+    cmd.insert_resource(playback::Recording::new(&grid));
     cmd.insert_resource(grid);
 }
 pub fn splash_off(mut state: ResMut<State<AppState>>) {
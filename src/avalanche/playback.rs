@@ -0,0 +1,153 @@
+use bevy::{prelude::*, time::Stopwatch};
+use std::time::Duration;
+
+use crate::engine::{Action, Game, MoveReport, Replay, ReplayMove};
+
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_PATH: &str = "avalanche.replay.ron";
+
+/// The replay of the game being played, with the clock for its timestamps
+pub struct Recording {
+    pub replay: Replay,
+    pub clock: Stopwatch,
+}
+impl Recording {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            replay: Replay::new(game),
+            clock: Stopwatch::new(),
+        }
+    }
+    pub fn record(&mut self, action: Action) {
+        self.replay.record(action, self.clock.elapsed());
+    }
+}
+
+/// Present while a replay is fed back into the [`Game`]; player input is
+/// ignored meanwhile.
+pub struct Playback {
+    pub replay: Replay,
+    pub next: usize,
+    pub clock: Duration,
+    pub speed: u32,
+    pub paused: bool,
+}
+impl Playback {
+    pub const fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            clock: Duration::ZERO,
+            speed: 1,
+            paused: false,
+        }
+    }
+    /// Plays the next action right away, whatever its timestamp
+    fn step(&mut self, game: &mut Game) -> Option<MoveReport> {
+        let &ReplayMove(action, at) = self.replay.moves.get(self.next)?;
+        self.clock = self.clock.max(Duration::from_millis(at.into()));
+        self.next += 1;
+        action
+            .apply(game)
+            .map_err(|e| warn!("replay diverged at move {}: {e}", self.next))
+            .ok()
+            .flatten()
+    }
+    pub fn is_done(&self) -> bool {
+        self.next >= self.replay.moves.len()
+    }
+}
+
+pub fn tick_recording(time: Res<Time>, recording: Option<ResMut<Recording>>) {
+    if let Some(mut recording) = recording {
+        recording.clock.tick(time.delta());
+    }
+}
+
+/// `P` replays the current game from its start, `F6` writes its replay to
+/// disk and `F7` plays the one on disk.
+pub fn replay_controls(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    recording: Option<Res<Recording>>,
+    mut game: ResMut<Game>,
+) {
+    let replay = if keys.just_pressed(KeyCode::P) {
+        recording.map(|r| r.replay.clone())
+    } else {
+        disk_replay(&keys, recording.as_deref())
+    };
+    if let Some(replay) = replay {
+        match replay.game() {
+            Ok(start) => {
+                *game = start;
+                cmd.insert_resource(Playback::new(replay));
+            }
+            Err(e) => warn!("{e}"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn disk_replay(keys: &Input<KeyCode>, recording: Option<&Recording>) -> Option<Replay> {
+    if keys.just_pressed(KeyCode::F6) {
+        match recording.map(|r| r.replay.save(REPLAY_PATH)) {
+            Some(Ok(())) => info!("replay saved to {REPLAY_PATH}"),
+            Some(Err(e)) => warn!("{e}"),
+            None => warn!("this game is not being recorded"),
+        }
+    } else if keys.just_pressed(KeyCode::F7) {
+        return Replay::load(REPLAY_PATH).map_err(|e| warn!("{e}")).ok();
+    }
+    None
+}
+#[cfg(target_arch = "wasm32")]
+const fn disk_replay(_: &Input<KeyCode>, _: Option<&Recording>) -> Option<Replay> {
+    None
+}
+
+/// Feeds the replay into the game. `Space` pauses, `N` steps one move and
+/// `1`, `2` or `8` set the speed.
+pub fn playback(
+    mut cmd: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    playback: Option<ResMut<Playback>>,
+    mut reports: EventWriter<MoveReport>,
+    mut game: ResMut<Game>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    for key in keys.get_just_pressed() {
+        match key {
+            KeyCode::Space => playback.paused = !playback.paused,
+            KeyCode::N => reports.send_batch(playback.step(&mut game).into_iter()),
+            KeyCode::Key1 => playback.speed = 1,
+            KeyCode::Key2 => playback.speed = 2,
+            KeyCode::Key8 => playback.speed = 8,
+            _ => {}
+        }
+    }
+    if !playback.paused {
+        playback.clock += time.delta() * playback.speed;
+        while let Some(&ReplayMove(_, at)) = playback.replay.moves.get(playback.next) {
+            if Duration::from_millis(at.into()) > playback.clock {
+                break;
+            }
+            reports.send_batch(playback.step(&mut game).into_iter());
+        }
+    }
+    if playback.is_done() {
+        info!("replay finished: {}", game.status());
+        // the player takes over from here, on top of the replayed moves
+        let mut clock = Stopwatch::new();
+        clock.set_elapsed(playback.clock);
+        cmd.insert_resource(Recording {
+            replay: playback.replay.clone(),
+            clock,
+        });
+        cmd.remove_resource::<Playback>();
+    }
+}
@@ -0,0 +1,359 @@
+use bevy::prelude::*;
+
+use super::{
+    assets::{BoardAssets, ShapePack, ShapeSetAsset},
+    components::{BrickTag, DotText, HistoryButton, HoldSq, Idx, TraySq},
+    playback::{Playback, Recording},
+    HoldSlot, Preview, ScoreBoard,
+};
+use crate::engine::{
+    face_value, Action, Dir, Dot, Game, GameStatus, MoveReport, ShapePool, Sq, MAX_LEVEL,
+};
+
+/// Feeds the arrow keys into the rules engine, one move per key press; with
+/// `Shift` held, the arrow holds a brick of that tray instead
+pub fn move_bricks(
+    keys: Res<Input<KeyCode>>,
+    playback: Option<Res<Playback>>,
+    recording: Option<ResMut<Recording>>,
+    mut reports: EventWriter<MoveReport>,
+    mut game: ResMut<Game>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    let dir = keys.get_just_pressed().find_map(|key| match key {
+        KeyCode::Up => Some(Dir::Up),
+        KeyCode::Down => Some(Dir::Down),
+        KeyCode::Left => Some(Dir::Left),
+        KeyCode::Right => Some(Dir::Right),
+        _ => None,
+    });
+    let action = match dir {
+        Some(dir) if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) => {
+            Action::Hold(dir)
+        }
+        Some(dir) => Action::Move(dir),
+        None => return,
+    };
+    match action.apply(&mut game) {
+        Ok(report) => {
+            if let Some(mut recording) = recording {
+                recording.record(action);
+            }
+            if let Some(report) = report {
+                if report.status.is_over() {
+                    info!("{}", report.status);
+                }
+                reports.send(report);
+            }
+        }
+        Err(e) => warn!("{e}"),
+    }
+}
+
+/// Undo with `U` or redo with `R`, or through the on-screen buttons
+pub fn undo_redo(
+    keys: Res<Input<KeyCode>>,
+    buttons: Query<(&Interaction, &HistoryButton), Changed<Interaction>>,
+    playback: Option<Res<Playback>>,
+    recording: Option<ResMut<Recording>>,
+    mut game: ResMut<Game>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    let pressed = buttons
+        .iter()
+        .find(|(&i, _)| i == Interaction::Clicked)
+        .map(|(_, &b)| b);
+    let undo = if keys.just_pressed(KeyCode::U) || pressed == Some(HistoryButton::Undo) {
+        true
+    } else if keys.just_pressed(KeyCode::R) || pressed == Some(HistoryButton::Redo) {
+        false
+    } else {
+        return;
+    };
+    match (undo, recording) {
+        (true, recording) if game.undo().is_ok() => {
+            if let Some(mut recording) = recording {
+                recording.replay.undo();
+            }
+        }
+        (false, recording) if game.redo().is_ok() => {
+            if let Some(mut recording) = recording {
+                recording.replay.redo();
+            }
+        }
+        _ => info!("nothing to {}", if undo { "undo" } else { "redo" }),
+    }
+}
+
+/// `PageUp` and `PageDown` pick the start level, until the first move
+pub fn pick_level(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    playback: Option<Res<Playback>>,
+    mut game: ResMut<Game>,
+) {
+    if playback.is_some() || game.turn() > 0 {
+        return;
+    }
+    let start = game.config().start_level;
+    let start = if keys.just_pressed(KeyCode::PageUp) {
+        start.saturating_add(1).min(MAX_LEVEL)
+    } else if keys.just_pressed(KeyCode::PageDown) {
+        start.saturating_sub(1).max(1)
+    } else {
+        return;
+    };
+    let config = game.config().clone().with_start_level(start);
+    match Game::from_config(config, rand::random()) {
+        Ok(new) => {
+            *game = new;
+            cmd.insert_resource(Recording::new(&game));
+        }
+        Err(e) => warn!("{e}"),
+    }
+}
+
+/// Picks the shape pool with `3`, `4` or `5` until the first move
+pub fn pick_shapes(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    playback: Option<Res<Playback>>,
+    mut game: ResMut<Game>,
+) {
+    if playback.is_some() || game.turn() > 0 {
+        return;
+    }
+    let pool = if keys.just_pressed(KeyCode::Key3) {
+        ShapePool::Trominoes
+    } else if keys.just_pressed(KeyCode::Key4) {
+        ShapePool::Tetrominoes
+    } else if keys.just_pressed(KeyCode::Key5) {
+        ShapePool::Pentominoes
+    } else {
+        return;
+    };
+    let config = game.config().clone().with_shapes(pool.shapes());
+    match Game::from_config(config, rand::random()) {
+        Ok(new) => {
+            *game = new;
+            cmd.insert_resource(Recording::new(&game));
+        }
+        Err(e) => warn!("{e}"),
+    }
+}
+
+/// Starts over with the shapes of the [`ShapePack`] once it loads, and
+/// again whenever its file changes
+pub fn apply_shape_pack(
+    mut cmd: Commands,
+    pack: Res<ShapePack>,
+    sets: Res<Assets<ShapeSetAsset>>,
+    mut game: ResMut<Game>,
+) {
+    if !sets.is_changed() {
+        return;
+    }
+    let Some(set) = sets.get(&pack.0) else {
+        return;
+    };
+    if game.config().shapes == **set {
+        return;
+    }
+    info!("dealing from the {} shapes", set.name);
+    let config = game.config().clone().with_shapes(set.0.clone());
+    match Game::from_config(config, rand::random()) {
+        Ok(new) => {
+            *game = new;
+            cmd.insert_resource(Recording::new(&game));
+        }
+        Err(e) => warn!("{e}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_PATH: &str = "avalanche.ron";
+
+/// Writes the session to disk with `F5` and resumes it with `F9`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_load(mut cmd: Commands, keys: Res<Input<KeyCode>>, mut game: ResMut<Game>) {
+    if keys.just_pressed(KeyCode::F5) {
+        match game.save(SAVE_PATH) {
+            Ok(()) => info!("saved to {SAVE_PATH}"),
+            Err(e) => warn!("{e}"),
+        }
+    } else if keys.just_pressed(KeyCode::F9) {
+        match Game::load(SAVE_PATH) {
+            Ok(loaded) => {
+                // a resumed game does not start from its seed, so it can't be replayed
+                cmd.remove_resource::<Recording>();
+                *game = loaded;
+            }
+            Err(e) => warn!("{e}"),
+        }
+    }
+}
+
+/// Mirrors the state of the [`Game`] onto the squares of the board and trays,
+/// coloured by the rank of their dot and labelled with its face value, and
+/// what the last move scored onto the [`ScoreBoard`]. Obstacles get their
+/// own sprite.
+pub fn spawn_shape(
+    game: Res<Game>,
+    assets: Res<BoardAssets>,
+    mut squares: Query<(&Idx, Option<&TraySq>, &mut UiColor, &mut UiImage)>,
+    mut dots: Query<(&Parent, &mut Text), (With<DotText>, Without<ScoreBoard>)>,
+    mut score: Query<&mut Text, With<ScoreBoard>>,
+    mut reports: EventReader<MoveReport>,
+) {
+    let report = reports.iter().last();
+    if !game.is_changed() {
+        return;
+    }
+    let gained = match report {
+        Some(report) if report.points.total > 0 => format!("\n{}", report.points),
+        _ => String::new(),
+    };
+    for (idx, tray, mut color, mut image) in squares.iter_mut() {
+        let dir = tray.map(|t| &t.0);
+        let material = match game.get_dot_val(**idx, dir).unwrap_or_default() {
+            Sq::Empty | Sq::Dot(0) => &assets.sq,
+            Sq::Dot(v) => &assets.dot[(v - 1) as usize % assets.dot.len()],
+            Sq::Obstacle(obstacle) => &assets.obstacle[&obstacle],
+        };
+        color.0 = material.color;
+        image.0 = material.texture.clone();
+    }
+    for (parent, mut text) in dots.iter_mut() {
+        if let Ok((idx, tray, ..)) = squares.get(parent.get()) {
+            let dir = tray.map(|t| &t.0);
+            let rank = game.get_dot_val(**idx, dir).unwrap_or_default().rank();
+            text.sections[0].value = match rank {
+                0 => String::new(),
+                v => face_value(v).to_string(),
+            };
+        }
+    }
+    let level = game.level();
+    for mut text in score.iter_mut() {
+        text.sections[0].value = match game.status() {
+            GameStatus::Running => format!("Level {level}\nScore: {}{}", game.score(), gained),
+            status => format!("Level {level}\nScore: {}{}\n{status}", game.score(), gained),
+        };
+    }
+}
+
+/// Tags the squares holding a brick's dots with its [`BrickTag`], so
+/// animations and highlights can follow a brick from turn to turn
+pub fn tag_bricks(
+    mut cmd: Commands,
+    game: Res<Game>,
+    squares: Query<(Entity, &Idx, Option<&TraySq>, Option<&BrickTag>)>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    for (entity, idx, tray, tagged) in squares.iter() {
+        let dir = tray.map(|t| &t.0);
+        match (game.brick_at(**idx, dir).map(|b| b.1.id), tagged) {
+            (Some(id), Some(&BrickTag(tag))) if id == tag => {}
+            (Some(id), _) => {
+                cmd.entity(entity).insert(BrickTag(id));
+            }
+            (None, Some(_)) => {
+                cmd.entity(entity).remove::<BrickTag>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Lists the next spawns, in the colour of their shape, and the tray each is
+/// headed for, beside the [`ScoreBoard`]
+pub fn show_preview(game: Res<Game>, mut preview: Query<&mut Text, With<Preview>>) {
+    if !game.is_changed() {
+        return;
+    }
+    let shapes = &game.config().shapes;
+    for mut text in preview.iter_mut() {
+        let style = text.sections[0].style.clone();
+        let heading = TextSection {
+            value: match game.upcoming().is_empty() {
+                true => String::new(),
+                false => "Next".to_string(),
+            },
+            style: style.clone(),
+        };
+        let next = game.upcoming().iter().map(|&(shape, dir)| {
+            let (r, g, b) = shapes.get(shape).map_or((255, 255, 255), |s| s.color);
+            TextSection {
+                value: format!("\n{} {dir:?}", shapes.name(shape)),
+                style: TextStyle {
+                    color: Color::rgb_u8(r, g, b),
+                    ..style.clone()
+                },
+            }
+        });
+        text.sections = std::iter::once(heading).chain(next).collect();
+    }
+}
+
+/// Draws the held brick into the hold slot
+pub fn show_hold(
+    game: Res<Game>,
+    assets: Res<BoardAssets>,
+    slots: Query<&HoldSlot>,
+    mut squares: Query<(&HoldSq, &mut UiColor)>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let Ok(HoldSlot { side, .. }) = slots.get_single() else {
+        return;
+    };
+    let mut slot = vec![0; side * side];
+    if let Some((dir, brick)) = game.held() {
+        let width = game.tray_width(*dir);
+        let (lo, _) = brick.bounds(width);
+        for (&k, &v) in brick.0.iter() {
+            let Dot(x, y) = Dot::from_idx(k.into(), width);
+            let (x, y) = ((x - lo.0) as usize, (y - lo.1) as usize);
+            if x < *side && y < *side {
+                slot[y * side + x] = v;
+            }
+        }
+    }
+    for (idx, mut color) in squares.iter_mut() {
+        color.0 = match slot.get(**idx).copied().unwrap_or_default() {
+            0 => assets.sq.color,
+            v => assets.dot[(v - 1) as usize % assets.dot.len()].color,
+        };
+    }
+}
+
+/// Grows or shrinks the [`HoldSlot`] to the longest shape of the game's set,
+/// after a switch to another set
+pub fn resize_hold(
+    mut cmd: Commands,
+    game: Res<Game>,
+    assets: Res<BoardAssets>,
+    mut slots: Query<(Entity, &mut HoldSlot, &mut Style)>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let side = game.config().shapes.extent() as usize;
+    for (entity, mut slot, mut style) in slots.iter_mut() {
+        if slot.side == side {
+            continue;
+        }
+        slot.side = side;
+        *style = slot.style();
+        let mut hold = cmd.entity(entity);
+        hold.despawn_descendants();
+        hold.with_children(|p| slot.spawn_squares(p, &assets));
+    }
+}
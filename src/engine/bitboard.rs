@@ -0,0 +1,241 @@
+use std::ops::{BitAnd, BitOr, Not};
+
+use super::{game::Sq, shapes::Dir};
+
+const BITS: usize = u64::BITS as usize;
+
+/// One bit per cell of a `width` x `height` grid, in row major order
+///
+/// Slides and line checks work a word at a time: moving every cell one step
+/// is a shift of the whole board, a full row is a run of set bits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard {
+    width: usize,
+    height: usize,
+    words: Vec<u64>,
+}
+
+impl Bitboard {
+    pub fn new(width: u8, height: u8) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            words: vec![0; (width * height).div_ceil(BITS)],
+        }
+    }
+    pub fn from_cells(width: u8, height: u8, cells: impl IntoIterator<Item = usize>) -> Self {
+        let mut ret = Self::new(width, height);
+        cells.into_iter().for_each(|i| ret.set(i));
+        ret
+    }
+    /// The occupied squares of a grid
+    pub fn from_grid(width: u8, height: u8, grid: &[Sq]) -> Self {
+        let mut ret = Self::new(width, height);
+        for (w, chunk) in ret.words.iter_mut().zip(grid.chunks(BITS)) {
+            *w = chunk
+                .iter()
+                .rev()
+                .fold(0, |w, &s| w << 1 | u64::from(!s.is_empty()));
+        }
+        ret
+    }
+    /// Every cell of the edge `dir` points at
+    pub fn edge(width: u8, height: u8, dir: Dir) -> Self {
+        let (w, h) = (width as usize, height as usize);
+        let cells: Box<dyn Iterator<Item = usize>> = match dir {
+            Dir::Up => Box::new((0..w).map(|c| (h - 1) * w + c)),
+            Dir::Down => Box::new(0..w),
+            Dir::Left => Box::new((0..h).map(|r| r * w)),
+            Dir::Right => Box::new((0..h).map(|r| r * w + w - 1)),
+        };
+        Self::from_cells(width, height, cells)
+    }
+
+    pub const fn len(&self) -> usize {
+        self.width * self.height
+    }
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len() && self.words[i / BITS] >> (i % BITS) & 1 == 1
+    }
+    pub fn set(&mut self, i: usize) {
+        if i < self.len() {
+            self.words[i / BITS] |= 1 << (i % BITS);
+        }
+    }
+    pub fn unset(&mut self, i: usize) {
+        if i < self.len() {
+            self.words[i / BITS] &= !(1 << (i % BITS));
+        }
+    }
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.words.iter().zip(&other.words).any(|(a, b)| a & b != 0)
+    }
+    /// Whether a cell is set both here and in `other`, leaving out the cells
+    /// of `except`
+    pub fn intersects_except(&self, other: &Self, except: &Self) -> bool {
+        let words = self.words.iter().zip(&other.words).zip(&except.words);
+        words.into_iter().any(|((a, b), c)| a & b & !c != 0)
+    }
+    /// Clears the cells of `from` and sets those of `to`
+    pub fn replace(&mut self, from: &Self, to: &Self) {
+        let words = self.words.iter_mut().zip(&from.words).zip(&to.words);
+        words.for_each(|((w, f), t)| *w = *w & !f | t);
+    }
+    /// Indices of the set cells, in order
+    pub fn cells(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &w)| {
+            let mut w = w;
+            std::iter::from_fn(move || {
+                (w != 0).then(|| {
+                    let bit = w.trailing_zeros() as usize;
+                    w &= w - 1;
+                    i * BITS + bit
+                })
+            })
+        })
+    }
+
+    /// Every cell moved one step towards `dir`; cells leaving the grid are lost
+    pub fn shifted(&self, dir: Dir) -> Self {
+        let (w, h) = (self.width as u8, self.height as u8);
+        match dir {
+            Dir::Up => self.shl(self.width),
+            Dir::Down => self.shr(self.width),
+            Dir::Left => (self & &!&Self::edge(w, h, Dir::Left)).shr(1),
+            Dir::Right => (self & &!&Self::edge(w, h, Dir::Right)).shl(1),
+        }
+    }
+    /// Every cell moved one step towards `dir`, for a board with no cell on
+    /// that edge; cheaper than [`Bitboard::shifted`], as nothing can leave
+    pub fn stepped(&self, dir: Dir) -> Self {
+        match dir {
+            Dir::Up => self.shl(self.width),
+            Dir::Down => self.shr(self.width),
+            Dir::Left => self.shr(1),
+            Dir::Right => self.shl(1),
+        }
+    }
+    /// Towards higher indices
+    fn shl(&self, by: usize) -> Self {
+        let (skip, bits) = (by / BITS, by % BITS);
+        let mut words = vec![0; self.words.len()];
+        for (i, word) in words.iter_mut().enumerate().skip(skip) {
+            let carry = match (bits, i.checked_sub(skip + 1)) {
+                (0, _) | (_, None) => 0,
+                (_, Some(j)) => self.words[j] >> (BITS - bits),
+            };
+            *word = self.words[i - skip] << bits | carry;
+        }
+        let mut ret = Self { words, ..*self };
+        ret.trim();
+        ret
+    }
+    /// Towards lower indices
+    fn shr(&self, by: usize) -> Self {
+        let (skip, bits) = (by / BITS, by % BITS);
+        let mut words = vec![0; self.words.len()];
+        for i in 0..words.len().saturating_sub(skip) {
+            let hi = self.words[i + skip];
+            let carry = match (bits, self.words.get(i + skip + 1)) {
+                (0, _) | (_, None) => 0,
+                (_, Some(&w)) => w << (BITS - bits),
+            };
+            words[i] = hi >> bits | carry;
+        }
+        Self { words, ..*self }
+    }
+    /// Clears the bits past the last cell
+    fn trim(&mut self) {
+        let tail = self.len() % BITS;
+        if let (Some(last), true) = (self.words.last_mut(), tail > 0) {
+            *last &= (1 << tail) - 1;
+        }
+    }
+
+    /// The `len` cells from `start`, `len` at most 64, as the low bits
+    fn bits(&self, start: usize, len: usize) -> u64 {
+        let (i, off) = (start / BITS, start % BITS);
+        let mut ret = self.words[i] >> off;
+        if off > 0 && off + len > BITS {
+            ret |= self.words[i + 1] << (BITS - off);
+        }
+        ret & ones(len)
+    }
+    /// Starts and lengths of the runs of at most 64 cells a row splits into
+    fn chunks(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..width)
+            .step_by(BITS)
+            .map(move |c| (c, (width - c).min(BITS)))
+    }
+    /// Rows with every cell set
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..self.height)
+            .filter(|&r| {
+                self.chunks()
+                    .all(|(c, len)| self.bits(r * self.width + c, len) == ones(len))
+            })
+            .collect()
+    }
+    /// Columns with every cell set
+    pub fn full_cols(&self) -> Vec<usize> {
+        let mut ret = vec![];
+        for (c, len) in self.chunks() {
+            let mut all =
+                (0..self.height).fold(ones(len), |acc, r| acc & self.bits(r * self.width + c, len));
+            while all != 0 {
+                ret.push(c + all.trailing_zeros() as usize);
+                all &= all - 1;
+            }
+        }
+        ret
+    }
+}
+
+/// The low `len` bits set
+const fn ones(len: usize) -> u64 {
+    match len {
+        0 => 0,
+        BITS.. => u64::MAX,
+        _ => (1 << len) - 1,
+    }
+}
+
+impl BitAnd for &Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Self) -> Bitboard {
+        let words = self.words.iter().zip(&rhs.words).map(|(a, b)| a & b);
+        Bitboard {
+            words: words.collect(),
+            ..*self
+        }
+    }
+}
+impl BitOr for &Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Self) -> Bitboard {
+        let words = self.words.iter().zip(&rhs.words).map(|(a, b)| a | b);
+        Bitboard {
+            words: words.collect(),
+            ..*self
+        }
+    }
+}
+impl Not for &Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        let mut ret = Bitboard {
+            words: self.words.iter().map(|w| !w).collect(),
+            ..*self
+        };
+        ret.trim();
+        ret
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    layout::Layout,
+    level::Progression,
+    randomizer::{ShapeRandomizer, Uniform},
+    scoring::Scoring,
+    shape_set::ShapeSet,
+};
+
+pub const DEFAULT_TRAY_DEPTH: u8 = 4;
+
+/// What a full row or column takes with it when it clears
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClearRule {
+    /// Only the dots on the line; the bricks crossing it are split
+    #[default]
+    Dots,
+    /// Every brick the line touches, Tetris style
+    Bricks,
+    /// Every brick the line touches, and their neighbours of the same shape
+    Shapes,
+}
+
+impl ClearRule {
+    /// Points for clearing `lines` lines, which took `bricks` whole bricks
+    /// with them plus `alike` neighbours of the same shape
+    pub const fn points(self, lines: usize, bricks: usize, alike: usize) -> u32 {
+        (match self {
+            Self::Dots => lines,
+            Self::Bricks => lines + bricks,
+            Self::Shapes => lines + bricks + 2 * alike,
+        }) as u32
+    }
+}
+
+/// Shape of a game: the board and the trays around it
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub height: u8,
+    pub width: u8,
+    /// How many cells deep each tray is, away from the board
+    pub tray_depth: u8,
+    /// Whether equal dots merge, Threes/2048 style, when one slides into the other
+    pub merge: bool,
+    /// Whether the pieces of a cleared brick fall towards the played direction
+    pub gravity: bool,
+    pub clear: ClearRule,
+    /// Whether a brick stopped by another off its centre tips over the corner
+    pub rotate: bool,
+    pub scoring: Scoring,
+    /// Level the game starts at, from `1`
+    pub start_level: u8,
+    pub progression: Progression,
+    /// Score that wins the game, if any
+    pub goal: Option<u32>,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    pub randomizer: ShapeRandomizer,
+    /// How many upcoming spawns the player can see, `0` to play blind
+    pub preview: u8,
+    /// Obstacles and bricks the board starts with
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    pub layout: Layout,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    pub shapes: ShapeSet,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self::new(7, 7)
+    }
+}
+
+impl GameConfig {
+    pub fn new(height: u8, width: u8) -> Self {
+        Self {
+            height,
+            width,
+            tray_depth: DEFAULT_TRAY_DEPTH,
+            merge: true,
+            gravity: true,
+            clear: ClearRule::Dots,
+            rotate: false,
+            scoring: Scoring::Classic,
+            start_level: 1,
+            progression: Progression::Turns(30),
+            goal: None,
+            randomizer: ShapeRandomizer::Uniform(Uniform),
+            preview: 3,
+            layout: Layout::default(),
+            shapes: ShapeSet::classic(),
+        }
+    }
+    pub const fn with_tray_depth(mut self, tray_depth: u8) -> Self {
+        self.tray_depth = tray_depth;
+        self
+    }
+    pub const fn with_merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+    pub const fn with_gravity(mut self, gravity: bool) -> Self {
+        self.gravity = gravity;
+        self
+    }
+    pub const fn with_clear_rule(mut self, clear: ClearRule) -> Self {
+        self.clear = clear;
+        self
+    }
+    pub const fn with_rotate(mut self, rotate: bool) -> Self {
+        self.rotate = rotate;
+        self
+    }
+    pub const fn with_scoring(mut self, scoring: Scoring) -> Self {
+        self.scoring = scoring;
+        self
+    }
+    pub const fn with_start_level(mut self, start_level: u8) -> Self {
+        self.start_level = start_level;
+        self
+    }
+    pub const fn with_progression(mut self, progression: Progression) -> Self {
+        self.progression = progression;
+        self
+    }
+    pub const fn with_goal(mut self, goal: Option<u32>) -> Self {
+        self.goal = goal;
+        self
+    }
+    pub fn with_randomizer(mut self, randomizer: ShapeRandomizer) -> Self {
+        self.randomizer = randomizer;
+        self
+    }
+    pub const fn with_preview(mut self, preview: u8) -> Self {
+        self.preview = preview;
+        self
+    }
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+    pub fn with_shapes(mut self, shapes: ShapeSet) -> Self {
+        self.shapes = shapes;
+        self
+    }
+}
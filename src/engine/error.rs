@@ -0,0 +1,58 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{game::GameStatus, shapes::Dir};
+
+/// Everything a move on a [`Game`](super::Game) can refuse with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// `play` was called without a direction in `Game::play`
+    NoDirection,
+    /// The game already ended with this status
+    GameOver(GameStatus),
+    /// No tray has room for another brick
+    TraysFull,
+    /// The tray on this side of the board is missing from the game
+    MissingTray(Dir),
+    /// There is no earlier move in the history
+    NothingToUndo,
+    /// There is no undone move in the history
+    NothingToRedo,
+    /// Only one hold is allowed each turn
+    HoldUsed,
+    /// Neither the tray on this side nor the hold slot has a brick
+    NothingToHold(Dir),
+    /// The held brick does not fit in the tray on this side
+    NoRoom(Dir),
+    /// A save file could not be written or parsed
+    Format(String),
+    /// A save file is from an unknown version of the format
+    UnsupportedVersion(u32),
+    /// Reading or writing a save file failed
+    Io(String),
+    /// A shape set could not be parsed, or has nothing to deal
+    BadShapeSet(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDirection => write!(f, "no direction to play"),
+            Self::GameOver(status) => write!(f, "game is over: {status}"),
+            Self::TraysFull => write!(f, "no tray can take another brick"),
+            Self::MissingTray(dir) => write!(f, "no tray on the {dir:?} side"),
+            Self::NothingToUndo => write!(f, "nothing to undo"),
+            Self::NothingToRedo => write!(f, "nothing to redo"),
+            Self::HoldUsed => write!(f, "already held a brick this turn"),
+            Self::NothingToHold(dir) => write!(f, "no brick to hold on the {dir:?} side"),
+            Self::NoRoom(dir) => write!(f, "no room for the held brick on the {dir:?} side"),
+            Self::Format(e) => write!(f, "bad save file: {e}"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported save version {v}"),
+            Self::Io(e) => write!(f, "{e}"),
+            Self::BadShapeSet(e) => write!(f, "bad shape set: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,1591 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::ops::Deref;
+use strum::IntoEnumIterator;
+use tracing::trace;
+
+use super::bitboard::Bitboard;
+use super::config::{ClearRule, GameConfig};
+use super::error::{Error, Result};
+use super::history::{History, Snapshot};
+use super::layout::{Layout, Obstacle};
+use super::level::{spawns, tray_space};
+use super::polyomino;
+use super::randomizer::{Randomizer, ShapeRandomizer};
+use super::report::{BrickMove, CascadeStep, MoveReport, TrayEntry};
+use super::scoring::{LineClear, ScoreBreakdown, ScoringRule};
+use super::shapes::{Brick, BrickId, Cell, Dir, Dot, Lineage, Shape};
+
+/// A square of the board or a tray
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sq {
+    #[default]
+    Empty,
+    /// A dot of a brick, by its rank from `1`
+    Dot(u8),
+    Obstacle(Obstacle),
+}
+impl Sq {
+    pub const fn is_empty(self) -> bool {
+        matches!(self, Self::Empty)
+    }
+    /// Rank of the dot on the square, `0` when there is none
+    pub const fn rank(self) -> u8 {
+        match self {
+            Self::Dot(rank) => rank,
+            _ => 0,
+        }
+    }
+    /// Whether a full line empties the square
+    pub const fn clears(self) -> bool {
+        !matches!(self, Self::Obstacle(Obstacle::Wall))
+    }
+}
+
+/// What a dot of rank `rank` is worth, `2^rank`, or `0` for rank `0`
+pub const fn face_value(rank: u8) -> u64 {
+    match rank {
+        0 => 0,
+        _ => match 1u64.checked_shl(rank as u32) {
+            Some(v) => v,
+            None => u64::MAX,
+        },
+    }
+}
+
+/// Why a game was lost
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LossReason {
+    /// No direction moves a brick or brings one in from a tray
+    #[default]
+    Stuck,
+    /// No tray has room for a new brick
+    TraysFull,
+}
+
+/// Where a game stands, recomputed after every move
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameStatus {
+    #[default]
+    Running,
+    Won,
+    Lost(LossReason),
+}
+impl GameStatus {
+    pub const fn is_over(self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Running => write!(f, "Running"),
+            Self::Won => write!(f, "Won"),
+            Self::Lost(LossReason::Stuck) => write!(f, "Lost, no move left"),
+            Self::Lost(LossReason::TraysFull) => write!(f, "Lost, trays are full"),
+        }
+    }
+}
+
+fn can_occupy(grid: &[Sq], brick: &Brick) -> bool {
+    trace!("{brick:?}");
+    brick
+        .cells()
+        .all(|d| grid.get(d).is_some_and(|s| s.is_empty()))
+}
+
+fn occupy(grid: &mut [Sq], brick: &Brick) -> bool {
+    let can_occupy = can_occupy(grid, brick);
+    if can_occupy {
+        brick
+            .0
+            .iter()
+            .for_each(|(&d, &v)| grid[d as usize] = Sq::Dot(v.max(1)))
+    }
+    can_occupy
+}
+
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    grid: Vec<Sq>,
+    tray: HashMap<Dir, Vec<Sq>>,
+    bricks: Vec<Brick>,
+    pub tray_bricks: HashMap<Dir, Vec<Brick>>,
+    config: GameConfig,
+    turn: u32,
+    score: u32,
+    /// Consecutive turns, up to the last, that cleared a line
+    streak: u32,
+    pub play: Option<Dir>,
+    status: GameStatus,
+    seed: u64,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    rng: Pcg32,
+    /// Running copy of the randomizer in the config
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    randomizer: ShapeRandomizer,
+    /// Shapes and trays of the next spawns, dealt ahead for the preview
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    upcoming: VecDeque<(Shape, Dir)>,
+    /// The brick out of play, and the tray it was taken from
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    held: Option<(Dir, Brick)>,
+    /// Turn of the last hold
+    held_on: Option<u32>,
+    /// Id of the next brick to come into play or split off
+    next_brick: BrickId,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    #[serde(skip)]
+    history: History,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    #[serde(skip)]
+    boards: Boards,
+}
+
+/// The board as bitboards, kept in step with the grid and the bricks on it
+/// as they change, so that slides and line checks never rebuild them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Boards {
+    /// Squares that are not empty
+    occupied: Bitboard,
+    /// Squares that never clear
+    walls: Bitboard,
+    /// Cells of each brick on the board, in the order of `Game::bricks`
+    bricks: Vec<Bitboard>,
+    /// Cells along each edge, in `Dir` order
+    edges: Vec<Bitboard>,
+}
+
+impl Game {
+    pub fn init(height: u8, width: u8) -> Self {
+        Self::with_seed(height, width, rand::random())
+    }
+    /// Same `seed` and same moves give the same game, on every platform
+    pub fn with_seed(height: u8, width: u8, seed: u64) -> Self {
+        Self::from_config(GameConfig::new(height, width), seed)
+            .expect("the classic shape set is valid")
+    }
+    /// A new game of `config`; a zero height, width or tray depth is taken as
+    /// `1`, the smallest board and trays a brick can be dealt into. Fails on
+    /// a shape set that [`ShapeSet::validate`](super::ShapeSet::validate)
+    /// rejects.
+    pub fn from_config(config: GameConfig, seed: u64) -> Result<Self> {
+        config.shapes.validate()?;
+        let config = GameConfig {
+            height: config.height.max(1),
+            width: config.width.max(1),
+            tray_depth: config.tray_depth.max(1),
+            ..config
+        };
+        let GameConfig {
+            height,
+            width,
+            tray_depth,
+            ..
+        } = config;
+        let randomizer = config.randomizer.clone();
+        let mut ret = Self {
+            grid: vec![Sq::Empty; height as usize * width as usize],
+            bricks: vec![],
+            tray: Dir::iter()
+                .map(|dir| {
+                    let len = dir.if_h(height, width) as usize * tray_depth as usize;
+                    (dir, vec![Sq::Empty; len])
+                })
+                .collect(),
+            tray_bricks: Dir::iter().map(|dir| (dir, vec![])).collect(),
+            config,
+            turn: 0,
+            score: 0,
+            streak: 0,
+            play: None,
+            status: GameStatus::Running,
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+            randomizer,
+            upcoming: VecDeque::new(),
+            held: None,
+            held_on: None,
+            next_brick: BrickId::default(),
+            history: History::default(),
+            boards: Boards::default(),
+        };
+        let layout = ret.config.layout.clone();
+        ret.lay_out(&layout);
+        ret.rebuild_boards();
+        ret.fill_preview();
+        if ret.gen_tray_brick().is_err() {
+            ret.status = GameStatus::Lost(LossReason::TraysFull);
+        }
+        Ok(ret)
+    }
+    /// Puts the obstacles and bricks of `layout` on the empty board
+    fn lay_out(&mut self, layout: &Layout) {
+        let (width, height) = (self.width(), self.height());
+        let on_board = |d: &Dot| d.0 < width && d.1 < height;
+        for &(at, obstacle) in layout.obstacles.iter().filter(|(d, _)| on_board(d)) {
+            let sq = &mut self.grid[at.to_idx(0, width)];
+            if sq.is_empty() {
+                *sq = Sq::Obstacle(obstacle);
+            }
+        }
+        for dots in &layout.bricks {
+            if !dots.iter().all(|(d, _)| on_board(d)) {
+                continue;
+            }
+            let brick = Brick(
+                dots.iter()
+                    .map(|&(d, rank)| (d.to_idx(0, width) as Cell, rank.max(1)))
+                    .collect(),
+                Lineage::spawn(None, self.next_brick),
+            );
+            if occupy(&mut self.grid, &brick) {
+                self.bricks.push(brick);
+                self.next_brick.issue();
+            }
+        }
+    }
+    /// Builds the bitboards over from the grid and bricks, after the board
+    /// was replaced as a whole
+    pub(super) fn rebuild_boards(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        let walls = (0..self.grid.len()).filter(|&i| !self.grid[i].clears());
+        self.boards = Boards {
+            occupied: Bitboard::from_grid(width, height, &self.grid),
+            walls: Bitboard::from_cells(width, height, walls),
+            bricks: self.bricks.iter().map(|b| self.mask(b)).collect(),
+            edges: Dir::iter()
+                .map(|dir| Bitboard::edge(width, height, dir))
+                .collect(),
+        };
+    }
+    fn mask(&self, brick: &Brick) -> Bitboard {
+        Bitboard::from_cells(self.width(), self.height(), brick.cells())
+    }
+    /// Puts `brick` in play, over squares already filled in on the grid
+    fn push_brick(&mut self, brick: Brick) {
+        let mask = self.mask(&brick);
+        self.boards.occupied = &self.boards.occupied | &mask;
+        self.boards.bricks.push(mask);
+        self.bricks.push(brick);
+    }
+    /// Takes the bricks left without a dot off the board
+    fn drop_empty_bricks(&mut self) {
+        let masks = std::mem::take(&mut self.boards.bricks);
+        (self.bricks, self.boards.bricks) = std::mem::take(&mut self.bricks)
+            .into_iter()
+            .zip(masks)
+            .filter(|(b, _)| !b.0.is_empty())
+            .unzip();
+    }
+    pub fn bricks(&self) -> &[Brick] {
+        &self.bricks
+    }
+    pub const fn width(&self) -> u8 {
+        self.config.width
+    }
+    pub const fn height(&self) -> u8 {
+        self.config.height
+    }
+    pub const fn tray_depth(&self) -> u8 {
+        self.config.tray_depth
+    }
+    pub const fn config(&self) -> &GameConfig {
+        &self.config
+    }
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Width of the tray grid on the `dir` side of the board
+    pub fn tray_width(&self, dir: Dir) -> u8 {
+        dir.if_h(self.tray_depth(), self.width())
+    }
+    /// Difficulty the game is at, from its start level, score and turns
+    pub fn level(&self) -> u8 {
+        let GameConfig {
+            start_level,
+            progression,
+            ..
+        } = self.config;
+        progression.level(start_level, self.score, self.turn)
+    }
+    pub fn tray(&self, dir: Dir) -> &[Sq] {
+        self.tray.get(&dir).map_or(&[], Vec::as_slice)
+    }
+    /// Moves every tray brick that fits onto the board, flush against the
+    /// `dir` edge and aligned with its place along that edge.
+    fn tray_to_grid(&mut self, dir: &Dir) -> Result<Vec<TrayEntry>> {
+        if !self.tray.contains_key(dir) {
+            return Err(Error::MissingTray(*dir));
+        }
+        let mut bricks = self
+            .tray_bricks
+            .remove(dir)
+            .ok_or(Error::MissingTray(*dir))?;
+        let mut placed = bricks
+            .iter()
+            .map(|b| self.entering(*dir, b))
+            .collect::<Vec<_>>()
+            .into_iter();
+        let mut entered = vec![];
+        bricks.retain(|brick| {
+            let Some(moved) = placed.next().flatten() else {
+                return true;
+            };
+            let occupied = occupy(&mut self.grid, &moved);
+            if occupied {
+                entered.push(TrayEntry {
+                    id: moved.1.id,
+                    dir: *dir,
+                    from: brick.sorted_cells(),
+                    to: moved.sorted_cells(),
+                });
+                self.push_brick(moved);
+                let tray = self.tray.get_mut(dir).expect("checked above");
+                brick.cells().for_each(|d| tray[d] = Sq::Empty);
+            }
+            !occupied
+        });
+        self.tray_bricks.insert(*dir, bricks);
+        Ok(entered)
+    }
+    /// Where `brick` of the `dir` tray lands on the board, against its edge;
+    /// `None` when the brick is wider or taller than the board.
+    fn entering(&self, dir: Dir, brick: &Brick) -> Option<Brick> {
+        let tw = self.tray_width(dir);
+        let (width, height) = (self.width(), self.height());
+        let (lo, _) = brick.bounds(tw);
+        let (bw, bh) = (brick.width(tw), brick.height(tw));
+        trace!("{bh} {bw}");
+        if bw > width || bh > height {
+            return None;
+        }
+        Some(brick.map_dots(tw, width, |Dot(x, y)| match dir {
+            Dir::Left => Dot(x - lo.0, y),
+            Dir::Right => Dot(width - bw + x - lo.0, y),
+            Dir::Down => Dot(x, y - lo.1),
+            Dir::Up => Dot(x, height - bh + y - lo.1),
+        }))
+    }
+    /// The brick with a dot on the square `id` of the board, or of the `dir`
+    /// tray
+    pub fn brick_at(&self, id: usize, dir: Option<&Dir>) -> Option<&Brick> {
+        let bricks = match dir {
+            Some(dir) => self.tray_bricks.get(dir)?,
+            None => &self.bricks,
+        };
+        let id = Cell::try_from(id).ok()?;
+        bricks.iter().find(|b| b.0.contains_key(&id))
+    }
+    /// The brick numbered `id`, on the board, in a tray or held
+    pub fn brick(&self, id: BrickId) -> Option<&Brick> {
+        self.bricks
+            .iter()
+            .chain(self.tray_bricks.values().flatten())
+            .chain(self.held.iter().map(|(_, b)| b))
+            .find(|b| b.1.id == id && !b.0.is_empty())
+    }
+    pub fn get_dot_val(&self, id: usize, dir: Option<&Dir>) -> Option<Sq> {
+        dir.map_or_else(|| self.grid.get(id), |&dir| self.tray(dir).get(id))
+            .copied()
+    }
+    /// The next spawns, soonest first, as many as the config previews
+    pub const fn upcoming(&self) -> &VecDeque<(Shape, Dir)> {
+        &self.upcoming
+    }
+    /// Deals a spawn: a shape of the level's pool from the randomizer, for a
+    /// random tray
+    fn deal(&mut self) -> (Shape, Dir) {
+        let pool = self.config.shapes.pool(self.level());
+        let shape = self
+            .randomizer
+            .next_shape(&pool, &self.config.shapes, &mut self.rng);
+        let dir = Dir::iter()
+            .nth(self.rng.gen_range(0..4u32) as usize)
+            .unwrap_or_default();
+        (shape, dir)
+    }
+    fn fill_preview(&mut self) {
+        while self.upcoming.len() < self.config.preview as usize {
+            let next = self.deal();
+            self.upcoming.push_back(next);
+        }
+    }
+    /// Puts the next brick into a tray with room for it, and tells where it
+    /// went. The level decides which shapes can come, and how deep into a
+    /// tray they may reach.
+    ///
+    /// The brick is the one dealt for the preview, in its tray if it fits
+    /// there, else in any tray; if it fits nowhere, one of the shapes that do
+    /// is picked evenly instead. That pick is not dealt, so the randomizer and
+    /// the preview carry on as they were. The brick's place is picked evenly.
+    pub fn gen_tray_brick(&mut self) -> Result<(Dir, Vec<usize>)> {
+        let (want, tray) = self.upcoming.pop_front().unwrap_or_else(|| self.deal());
+        self.fill_preview();
+        let shapes = &self.config.shapes;
+        let pool = shapes.pool(self.level());
+        let depth = tray_space(self.level(), self.tray_depth());
+        let variants: Vec<_> = shapes
+            .all()
+            .zip(&shapes.shapes)
+            .filter(|(shape, _)| pool.contains(shape))
+            .flat_map(|(shape, def)| def.orientations().map(move |(d, dots)| (shape, d, dots)))
+            .collect();
+        // Trays are walked in `Dir` order, not `HashMap` order, and the pick is
+        // a `u32` range so the same seed agrees on native and wasm32.
+        let mut placements: Vec<_> = Dir::iter()
+            .flat_map(|dir| {
+                let grid = self.tray(dir);
+                let dimension = dir.if_h(self.height(), self.width());
+                let width = self.tray_width(dir);
+                variants.iter().flat_map(move |(shape, d, dots)| {
+                    // only the picked placement is built; the rest are checked
+                    // by offsetting the cells of the brick at the origin
+                    let b = Brick::from_dots(dots, 0, width);
+                    let cells = b.sorted_cells();
+                    let max = dimension
+                        .checked_sub(b.dim_in(dir.turn(), width))
+                        .filter(|_| b.dim_in(dir, width) <= depth);
+                    max.into_iter()
+                        .flat_map(|max| 0..=max)
+                        .map(move |p| p as usize * dir.if_h(width, 1) as usize)
+                        .filter(move |&orig| {
+                            cells
+                                .iter()
+                                .all(|&c| grid.get(c + orig) == Some(&Sq::Empty))
+                        })
+                        .map(move |orig| (dir, *shape, (*d, orig)))
+                })
+            })
+            .collect();
+        if placements.is_empty() {
+            return Err(Error::TraysFull);
+        }
+        if placements.iter().any(|&(d, s, _)| (d, s) == (tray, want)) {
+            placements.retain(|&(d, s, _)| (d, s) == (tray, want));
+        } else {
+            let fits: Vec<_> = shapes
+                .all()
+                .filter(|&s| placements.iter().any(|&(_, shape, _)| shape == s))
+                .collect();
+            let shape = match fits.contains(&want) {
+                true => want,
+                false => fits[self.rng.gen_range(0..fits.len() as u32) as usize],
+            };
+            placements.retain(|&(_, s, _)| s == shape);
+        }
+        let pick = self.rng.gen_range(0..placements.len() as u32) as usize;
+        let (dir, shape, (d, orig)) = placements.swap_remove(pick);
+        let width = self.tray_width(dir);
+        let mut brick = shapes.brick(shape, d, orig, width).unwrap_or_default();
+        brick.1 = Lineage::spawn(Some(shape), self.next_brick.issue());
+        // ranks are handed out in cell order so the draws match on every run
+        for k in brick.sorted_cells() {
+            let rank = if self.rng.gen_ratio(1, 4) { 2 } else { 1 };
+            brick.0.insert(k as Cell, rank);
+        }
+        occupy(
+            self.tray.get_mut(&dir).ok_or(Error::MissingTray(dir))?,
+            &brick,
+        );
+        let cells = brick.sorted_cells();
+        self.tray_bricks
+            .get_mut(&dir)
+            .ok_or(Error::MissingTray(dir))?
+            .push(brick);
+        Ok((dir, cells))
+    }
+
+    /// Slides every brick on the board one cell towards `dir`, if it can.
+    ///
+    /// With merging on, a dot blocked by an equal dot of another brick joins
+    /// it first, and the brick carries on without it. With rotation on, a
+    /// brick stopped off its centre tips over instead. Bricks listed in `ids`
+    /// stay put. Returns the moves and the cells that took in a merge.
+    fn slide(&mut self, dir: Dir, ids: Vec<usize>) -> (Vec<BrickMove>, Vec<usize>) {
+        let (width, height) = (self.width(), self.height());
+        let w = width as usize;
+        let edge = self.boards.edges[dir as usize].clone();
+        let delta = |x| match dir {
+            Dir::Up => x + w,
+            Dir::Down => x - w,
+            Dir::Left => x - 1,
+            Dir::Right => x + 1,
+        };
+        let inner = !&edge;
+        let mut done = vec![false; self.bricks.len()];
+        ids.into_iter().for_each(|i| done[i] = true);
+        let mut dirty = true;
+        let mut moved = Vec::new();
+        let mut merged = Vec::new();
+        while dirty {
+            dirty = false;
+            for i in 0..self.bricks.len() {
+                done.resize(self.bricks.len(), false);
+                if done[i] {
+                    continue;
+                }
+                let (mut ahead, mut at_edge, mut blocked) = self.ahead(i, dir, &edge, &inner);
+                // only a dot with another brick's dot ahead of it can merge
+                if blocked
+                    && self.config.merge
+                    && self.merge_blocked(i, |x| (!edge.get(x)).then(|| delta(x)), &mut merged)
+                {
+                    dirty = true;
+                    (ahead, at_edge, blocked) = self.ahead(i, dir, &edge, &inner);
+                }
+                let b = &mut self.bricks[i];
+                let mask = &self.boards.bricks[i];
+                if b.0.is_empty() || at_edge {
+                    done[i] = true;
+                    continue;
+                }
+                let (blocked, tipped) = match blocked {
+                    true => {
+                        let others = &self.boards.occupied & &!mask;
+                        let blocked: Vec<_> = (&others.stepped(dir.opp()) & mask).cells().collect();
+                        let tipped = self
+                            .config
+                            .rotate
+                            .then(|| b.tipped(dir, &blocked, width))
+                            .flatten()
+                            .filter(|t| t.cells().all(|d| d < others.len()))
+                            .map(|t| {
+                                let mask = Bitboard::from_cells(width, height, t.cells());
+                                (t, mask)
+                            })
+                            .filter(|(_, mask)| !mask.intersects(&others));
+                        (blocked, tipped)
+                    }
+                    false => (vec![], None),
+                };
+                if blocked.is_empty() || tipped.is_some() {
+                    let from = b.sorted_cells();
+                    b.cells().for_each(|d| self.grid[d] = Sq::Empty);
+                    let to = match tipped {
+                        Some((t, to)) => {
+                            b.0 = t.0;
+                            to
+                        }
+                        None => {
+                            b.0 =
+                                b.0.drain()
+                                    .map(|(k, v)| (delta(k as usize) as Cell, v))
+                                    .collect();
+                            ahead
+                        }
+                    };
+                    b.0.iter()
+                        .for_each(|(&d, &v)| self.grid[d as usize] = Sq::Dot(v));
+                    self.boards.occupied.replace(mask, &to);
+                    self.boards.bricks[i] = to;
+                    moved.push(BrickMove {
+                        id: b.1.id,
+                        from,
+                        to: b.sorted_cells(),
+                    });
+                    dirty = true;
+                    done[i] = true;
+                }
+            }
+        }
+        (moved, merged)
+    }
+
+    /// The cells brick `i` would take one step towards `dir`, leaving out
+    /// its dots on the `edge`, the cells off it; and whether it is on the
+    /// edge, and whether a dot of another brick is in its way
+    fn ahead(
+        &self,
+        i: usize,
+        dir: Dir,
+        edge: &Bitboard,
+        inner: &Bitboard,
+    ) -> (Bitboard, bool, bool) {
+        let mask = &self.boards.bricks[i];
+        let at_edge = mask.intersects(edge);
+        // a dot on the edge would wrap round to the next row
+        let ahead = match at_edge {
+            true => (mask & inner).stepped(dir),
+            false => mask.stepped(dir),
+        };
+        let blocked = ahead.intersects_except(&self.boards.occupied, mask);
+        (ahead, at_edge, blocked)
+    }
+
+    /// Joins every dot of brick `i` into the dot of another brick lying on
+    /// its `next` cell, when both have the same rank. The joined dot goes up
+    /// a rank and scores its new face value; each cell merges once a move.
+    fn merge_blocked(
+        &mut self,
+        i: usize,
+        next: impl Fn(usize) -> Option<usize>,
+        merged: &mut Vec<usize>,
+    ) -> bool {
+        let brick = &self.bricks[i];
+        let mut joins: Vec<(usize, usize)> = brick
+            .0
+            .iter()
+            .filter_map(|(&k, &v)| {
+                let to = next(k as usize)?;
+                let free = !merged.contains(&to) && !brick.0.contains_key(&(to as Cell));
+                (free && v > 0 && self.grid[to] == Sq::Dot(v)).then_some((k as usize, to))
+            })
+            .collect();
+        if joins.is_empty() {
+            return false;
+        }
+        joins.sort_unstable();
+        for &(from, to) in &joins {
+            let rank = self.grid[to].rank().saturating_add(1);
+            self.grid[from] = Sq::Empty;
+            self.boards.occupied.unset(from);
+            self.grid[to] = Sq::Dot(rank);
+            if let Some(v) = self
+                .bricks
+                .iter_mut()
+                .find_map(|b| b.0.get_mut(&(to as Cell)))
+            {
+                *v = rank;
+            }
+            let points = u32::try_from(face_value(rank)).unwrap_or(u32::MAX);
+            self.score = self.score.saturating_add(points);
+            merged.push(to);
+        }
+        let from: Vec<_> = joins.iter().map(|&(from, _)| from).collect();
+        let width = self.width();
+        let fragments = self.bricks[i].cut_at(&from, width, &mut self.next_brick);
+        self.boards.bricks[i] = self.mask(&self.bricks[i]);
+        fragments.into_iter().for_each(|b| self.push_brick(b));
+        true
+    }
+
+    /// 1. try to move bricks on the grid
+    /// 2. then bring from the tray
+    /// 3. clear full lines, refill the trays and settle the [`GameStatus`]
+    pub fn play(&mut self) -> Result<MoveReport> {
+        if self.status.is_over() {
+            return Err(Error::GameOver(self.status));
+        }
+        let dir = self.play.take().ok_or(Error::NoDirection)?;
+        let before = self.snapshot();
+        let score = self.score;
+        let (moved, merged) = self.slide(dir, vec![]);
+        let entered = match self.tray_to_grid(&dir.opp()) {
+            Ok(entered) => entered,
+            Err(e) => {
+                self.restore(before);
+                return Err(e);
+            }
+        };
+        self.history.record(before);
+        let (cleared, mut fragments, clear) = self.cut_full_lines();
+        let mut clears = vec![clear];
+        let mut cascades = vec![];
+        while self.config.gravity && !fragments.is_empty() {
+            let (fell, merged) = self.settle(dir, &fragments);
+            if fell.is_empty() && merged.is_empty() {
+                break;
+            }
+            let chain = cascades.len() as u32 + 1;
+            let (cleared, next, clear) = self.cut_full_lines();
+            fragments = next;
+            clears.push(LineClear { chain, ..clear });
+            cascades.push(CascadeStep {
+                chain,
+                fell,
+                merged,
+                cleared,
+            });
+        }
+        self.drop_empty_bricks();
+        let points = self.score_clears(score, &clears);
+        self.inc_turn();
+        let mut spawned = vec![];
+        self.status = GameStatus::Running;
+        for _ in 0..spawns(self.level()) {
+            match self.gen_tray_brick() {
+                Ok(brick) => spawned.push(brick),
+                Err(Error::TraysFull) => {
+                    self.status = GameStatus::Lost(LossReason::TraysFull);
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.status.is_over() {
+            self.status = self.compute_status();
+        }
+        Ok(MoveReport {
+            dir,
+            moved,
+            merged,
+            entered,
+            cleared,
+            cascades,
+            spawned,
+            score_delta: self.score - score,
+            points,
+            status: self.status,
+        })
+    }
+
+    /// Plays a whole turn towards `dir`
+    pub fn step(&mut self, dir: Dir) -> Result<MoveReport> {
+        self.play = Some(dir);
+        self.play()
+    }
+
+    /// Whether playing `dir` would move anything, on the board or from a tray
+    ///
+    /// A slide changes nothing unless its first pass moves or merges a brick,
+    /// and then nothing can enter unless it fits the board as it is now; so
+    /// both are checked on the current board, without playing the move.
+    pub fn is_productive(&self, dir: Dir) -> bool {
+        self.can_slide(dir) || self.can_enter(dir.opp())
+    }
+    fn can_slide(&self, dir: Dir) -> bool {
+        let (width, height) = (self.width(), self.height());
+        let w = width as usize;
+        let Boards {
+            occupied,
+            bricks,
+            edges,
+            ..
+        } = &self.boards;
+        let edge = &edges[dir as usize];
+        let bricks = self.bricks.iter().zip(bricks);
+        bricks.filter(|(b, _)| !b.0.is_empty()).any(|(b, mask)| {
+            let merges = self.config.merge
+                && b.0.iter().any(|(&k, &v)| {
+                    let k = k as usize;
+                    let to = match dir {
+                        _ if edge.get(k) => return false,
+                        Dir::Up => k + w,
+                        Dir::Down => k - w,
+                        Dir::Left => k - 1,
+                        Dir::Right => k + 1,
+                    };
+                    v > 0 && self.grid[to] == Sq::Dot(v) && !b.0.contains_key(&(to as Cell))
+                });
+            if merges || mask.intersects(edge) {
+                return merges;
+            }
+            if !mask.stepped(dir).intersects_except(occupied, mask) {
+                return true;
+            }
+            let others = occupied & &!mask;
+            let blocked: Vec<_> = (&others.stepped(dir.opp()) & mask).cells().collect();
+            self.config.rotate
+                && b.tipped(dir, &blocked, width).is_some_and(|t| {
+                    t.cells().all(|d| d < occupied.len())
+                        && !Bitboard::from_cells(width, height, t.cells()).intersects(&others)
+                })
+        })
+    }
+    fn can_enter(&self, dir: Dir) -> bool {
+        self.tray_bricks.get(&dir).is_some_and(|bricks| {
+            bricks
+                .iter()
+                .filter_map(|b| self.entering(dir, b))
+                .any(|b| can_occupy(&self.grid, &b))
+        })
+    }
+
+    fn compute_status(&self) -> GameStatus {
+        if self.config.goal.is_some_and(|goal| self.score >= goal) {
+            GameStatus::Won
+        } else if Dir::iter().any(|dir| self.is_productive(dir)) {
+            GameStatus::Running
+        } else {
+            GameStatus::Lost(LossReason::Stuck)
+        }
+    }
+    pub const fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// The held brick, keyed in the tray it was taken from
+    pub const fn held(&self) -> Option<&(Dir, Brick)> {
+        self.held.as_ref()
+    }
+    /// Whether [`Game::hold`] is still allowed this turn
+    pub fn can_hold(&self) -> bool {
+        !self.status.is_over() && self.held_on != Some(self.turn)
+    }
+    /// Takes the newest brick of the `dir` tray out of play, and puts the
+    /// brick held before, if any, into that tray instead. Once a turn, and
+    /// undone like a move.
+    pub fn hold(&mut self, dir: Dir) -> Result<()> {
+        if self.status.is_over() {
+            return Err(Error::GameOver(self.status));
+        }
+        if !self.can_hold() {
+            return Err(Error::HoldUsed);
+        }
+        let tray_bricks = self.tray_bricks.get(&dir).ok_or(Error::MissingTray(dir))?;
+        if tray_bricks.is_empty() && self.held.is_none() {
+            return Err(Error::NothingToHold(dir));
+        }
+        let before = self.snapshot();
+        let taken = self.tray_bricks.get_mut(&dir).and_then(Vec::pop);
+        if let (Some(brick), Some(tray)) = (&taken, self.tray.get_mut(&dir)) {
+            brick.cells().for_each(|d| tray[d] = Sq::Empty);
+        }
+        if let Some(held) = self.held.take() {
+            if let Err(e) = self.put_in_tray(dir, held) {
+                self.restore(before);
+                return Err(e);
+            }
+        }
+        self.held = taken.map(|brick| (dir, brick));
+        self.held_on = Some(self.turn);
+        self.history.record(before);
+        self.status = self.compute_status();
+        Ok(())
+    }
+    /// Puts the `from` tray's `brick` into the `dir` tray, at the first
+    /// place along the edge where it fits
+    fn put_in_tray(&mut self, dir: Dir, (from, brick): (Dir, Brick)) -> Result<()> {
+        let (fw, tw) = (self.tray_width(from), self.tray_width(dir));
+        let rows = dir.if_h(self.height(), self.tray_depth());
+        let (lo, _) = brick.bounds(fw);
+        let (bw, bh) = (brick.width(fw), brick.height(fw));
+        let length = dir.if_h(rows.checked_sub(bh), tw.checked_sub(bw));
+        let fits = bw <= tw && bh <= rows;
+        let tray = self.tray.get_mut(&dir).ok_or(Error::MissingTray(dir))?;
+        let placed = length
+            .filter(|_| fits)
+            .into_iter()
+            .flat_map(|max| 0..=max)
+            .map(|p| {
+                brick.map_dots(fw, tw, |Dot(x, y)| {
+                    let (x, y) = (x - lo.0, y - lo.1);
+                    dir.if_h(Dot(x, y + p), Dot(x + p, y))
+                })
+            })
+            .find(|b| occupy(tray, b))
+            .ok_or(Error::NoRoom(dir))?;
+        self.tray_bricks
+            .get_mut(&dir)
+            .ok_or(Error::MissingTray(dir))?
+            .push(placed);
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            grid: self.grid.clone(),
+            tray: self.tray.clone(),
+            bricks: self.bricks.clone(),
+            tray_bricks: self.tray_bricks.clone(),
+            turn: self.turn,
+            score: self.score,
+            streak: self.streak,
+            status: self.status,
+            rng: self.rng.clone(),
+            randomizer: self.randomizer.clone(),
+            upcoming: self.upcoming.clone(),
+            held: self.held.clone(),
+            held_on: self.held_on,
+            next_brick: self.next_brick,
+        }
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        let Snapshot {
+            grid,
+            tray,
+            bricks,
+            tray_bricks,
+            turn,
+            score,
+            streak,
+            status,
+            rng,
+            randomizer,
+            upcoming,
+            held,
+            held_on,
+            next_brick,
+        } = snapshot;
+        self.grid = grid;
+        self.tray = tray;
+        self.bricks = bricks;
+        self.tray_bricks = tray_bricks;
+        self.turn = turn;
+        self.score = score;
+        self.streak = streak;
+        self.status = status;
+        self.rng = rng;
+        self.randomizer = randomizer;
+        self.upcoming = upcoming;
+        self.held = held;
+        self.held_on = held_on;
+        self.next_brick = next_brick;
+        self.play = None;
+        self.rebuild_boards();
+    }
+    /// Takes back the last move, RNG included, so replaying it is identical
+    pub fn undo(&mut self) -> Result<()> {
+        let current = self.snapshot();
+        let prev = self.history.undo(current).ok_or(Error::NothingToUndo)?;
+        self.restore(prev);
+        Ok(())
+    }
+    pub fn redo(&mut self) -> Result<()> {
+        let current = self.snapshot();
+        let next = self.history.redo(current).ok_or(Error::NothingToRedo)?;
+        self.restore(next);
+        Ok(())
+    }
+    pub const fn history(&self) -> &History {
+        &self.history
+    }
+    /// How many moves can be taken back, `0` turns undo off
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Lets the `fragments` left by a clear slide towards `dir` until none
+    /// of them can move any further.
+    fn settle(&mut self, dir: Dir, fragments: &[usize]) -> (Vec<BrickMove>, Vec<usize>) {
+        let (mut fell, mut merged) = (vec![], vec![]);
+        loop {
+            let still = (0..self.bricks.len())
+                .filter(|i| !fragments.contains(i))
+                .collect();
+            let (moved, joined) = self.slide(dir, still);
+            if moved.is_empty() && joined.is_empty() {
+                return (fell, merged);
+            }
+            fell.extend(moved);
+            merged.extend(joined);
+        }
+    }
+
+    /// Scores the `clears` of a move on top of the merges that took the
+    /// score from `before` to where it is
+    fn score_clears(&mut self, before: u32, clears: &[LineClear]) -> ScoreBreakdown {
+        let clears: Vec<_> = clears.iter().filter(|c| c.lines() > 0).copied().collect();
+        self.streak = match clears.is_empty() {
+            true => 0,
+            false => self.streak + 1,
+        };
+        let mut points = self.config.scoring.score(&clears, self.streak);
+        points.merges = self.score - before;
+        points.total = points
+            .clears()
+            .saturating_mul(points.combo)
+            .saturating_add(points.merges);
+        self.score = before.saturating_add(points.total);
+        points
+    }
+
+    /// Empties every full row and column, and whatever the clear rule takes
+    /// along with them, splitting the bricks crossing them. Returns the
+    /// emptied cells and the indices of every brick that was cut or split
+    /// off, with the unscored clear; emptied bricks are left in place.
+    fn cut_full_lines(&mut self) -> (Vec<usize>, Vec<usize>, LineClear) {
+        let (width, height) = (self.width(), self.height());
+        let (w, h) = (width as usize, height as usize);
+        let Boards {
+            occupied, walls, ..
+        } = &self.boards;
+        // a line of walls alone never counts as full
+        let (wall_rows, wall_cols) = (walls.full_rows(), walls.full_cols());
+        let rows: Vec<_> = occupied
+            .full_rows()
+            .into_iter()
+            .filter(|r| !wall_rows.contains(r))
+            .collect();
+        let cols: Vec<_> = occupied
+            .full_cols()
+            .into_iter()
+            .filter(|c| !wall_cols.contains(c))
+            .collect();
+
+        let mut cleared: Vec<usize> = rows
+            .iter()
+            .flat_map(|r| (0..w).map(move |c| r * w + c))
+            .chain(cols.iter().flat_map(|c| (0..h).map(move |r| r * w + c)))
+            .filter(|&i| !walls.get(i))
+            .collect();
+        let (bricks, alike) = self.swept_bricks(&cleared);
+        let clear = LineClear {
+            rows: rows.len() as u32,
+            cols: cols.len() as u32,
+            points: self
+                .config
+                .clear
+                .points(rows.len() + cols.len(), bricks.len(), alike.len()),
+            chain: 0,
+        };
+        cleared.extend(
+            bricks
+                .iter()
+                .chain(&alike)
+                .flat_map(|&i| self.bricks[i].cells()),
+        );
+        cleared.sort_unstable();
+        cleared.dedup();
+        let mut fragments = vec![];
+        if !cleared.is_empty() {
+            let mut cleared_bricks: Vec<Brick> = vec![];
+            for i in 0..self.bricks.len() {
+                if self.bricks[i].contains_any(&cleared) {
+                    fragments.push(i);
+                    let next = &mut self.next_brick;
+                    cleared_bricks.append(&mut self.bricks[i].cut_at(&cleared, width, next));
+                    self.boards.bricks[i] = self.mask(&self.bricks[i]);
+                }
+            }
+            fragments.extend(self.bricks.len()..self.bricks.len() + cleared_bricks.len());
+            cleared_bricks.into_iter().for_each(|b| self.push_brick(b));
+            for &ele in cleared.iter() {
+                self.grid[ele] = Sq::Empty;
+                self.boards.occupied.unset(ele);
+            }
+        }
+        (cleared, fragments, clear)
+    }
+
+    /// The bricks the clear rule takes whole with the `line` cells, and the
+    /// neighbours of the same shape it takes along with them. Bricks are alike
+    /// when they cover the same cells once moved to the origin and turned, and,
+    /// when both still have every cell of the shape they were dealt as, were
+    /// dealt as the same one. A fragment, or a brick that lost cells, goes by
+    /// its cells alone: a dot left of an L is a dot, not an L.
+    fn swept_bricks(&self, line: &[usize]) -> (Vec<usize>, Vec<usize>) {
+        let w = self.width();
+        if line.is_empty() || self.config.clear == ClearRule::Dots {
+            return (vec![], vec![]);
+        }
+        let touched: Vec<_> = (0..self.bricks.len())
+            .filter(|&i| self.bricks[i].contains_any(line))
+            .collect();
+        let mut alike = vec![];
+        if self.config.clear == ClearRule::Shapes {
+            let dealt = |b: &Brick| {
+                let cells = b.footprint(w);
+                let whole = |s: &Shape| {
+                    let def = self.config.shapes.get(*s);
+                    def.is_some_and(|d| polyomino::rotations(&d.cells).contains(&cells))
+                };
+                (b.1.shape.filter(whole), cells)
+            };
+            let same = |a: &Brick, b: &Brick| {
+                let ((a_shape, a_cells), (b_shape, b_cells)) = (dealt(a), dealt(b));
+                match (a_shape, b_shape) {
+                    (Some(a), Some(b)) if a != b => false,
+                    _ => polyomino::rotations(&a_cells).contains(&b_cells),
+                }
+            };
+            for &t in touched.iter() {
+                for i in 0..self.bricks.len() {
+                    if !touched.contains(&i)
+                        && !alike.contains(&i)
+                        && self.bricks[i].touches(&self.bricks[t], w)
+                        && same(&self.bricks[i], &self.bricks[t])
+                    {
+                        alike.push(i);
+                    }
+                }
+            }
+        }
+        (touched, alike)
+    }
+
+    pub const fn turn(&self) -> u32 {
+        self.turn
+    }
+    pub const fn inc_turn(&mut self) {
+        self.turn = self.turn.wrapping_add(1);
+    }
+    pub const fn score(&self) -> u32 {
+        self.score
+    }
+}
+impl Deref for Game {
+    type Target = Vec<Sq>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        randomizer::Bag,
+        replay::Replay,
+        shape_set::{ShapeDef, ShapeSet},
+    };
+    use super::*;
+    use Dir::*;
+
+    /// Plays `turns` moves of a fixed pattern, stopping at game over
+    fn play_out(game: &mut Game, turns: u64, mut each: impl FnMut(&Game, &MoveReport)) {
+        for i in 0..turns {
+            let dir = Dir::iter().nth((i * 7 % 4) as usize).unwrap();
+            match game.step(dir) {
+                Ok(report) => each(game, &report),
+                Err(Error::GameOver(_)) => break,
+                Err(_) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn moves_report_the_id_of_the_brick_that_moved() {
+        let mut checked = 0;
+        for seed in 0..100 {
+            let config = GameConfig::new(6, 6).with_merge(false);
+            let mut game = Game::from_config(config, seed).unwrap();
+            play_out(&mut game, 100, |game, report| {
+                if !report.cleared.is_empty() || !report.cascades.is_empty() {
+                    return;
+                }
+                for m in &report.moved {
+                    let brick = game.brick(m.id).expect("a moved brick is still in play");
+                    assert_eq!(brick.sorted_cells(), m.to);
+                    checked += 1;
+                }
+            });
+        }
+        assert!(checked > 1000);
+    }
+
+    #[test]
+    fn a_seed_deals_the_same_spawns_on_every_platform() {
+        let mut game = Game::with_seed(7, 7, 42);
+        let dealt: Vec<_> = (0..6).map(|_| game.deal()).collect();
+        let want = [
+            (2, Down),
+            (0, Down),
+            (1, Up),
+            (1, Down),
+            (3, Down),
+            (4, Down),
+        ];
+        let want: Vec<_> = want.iter().map(|&(s, d)| (Shape(s), d)).collect();
+        assert_eq!(dealt, want);
+    }
+
+    #[test]
+    fn off_centre_contacts_tip_every_shape_into_a_turn_of_itself() {
+        let shapes = ShapeSet::classic();
+        let width = 11;
+        let mut tips = 0;
+        for (shape, d) in shapes.variants() {
+            let turns = polyomino::rotations(&shapes.get(shape).unwrap().cells);
+            let brick = shapes.brick(shape, d, Dot(4, 4).to_idx(0, width), width);
+            let brick = brick.unwrap();
+            let (lo, hi) = brick.bounds(width);
+            for dir in Dir::iter() {
+                for cell in brick.cells() {
+                    let Dot(x, y) = Dot::from_idx(cell, width);
+                    let (p, lo, hi) = dir.if_h((y, lo.1, hi.1), (x, lo.0, hi.0));
+                    let tipped = brick.tipped(dir, &[cell], width);
+                    if 2 * p == lo + hi {
+                        assert!(tipped.is_none(), "{shape:?} {d:?} tipped on its centre");
+                        continue;
+                    }
+                    let tipped = tipped.expect("an off-centre contact tips");
+                    assert!(turns.contains(&tipped.footprint(width)));
+                    assert_eq!(tipped.0.len(), brick.0.len());
+                    tips += 1;
+                }
+            }
+        }
+        assert!(tips > 100);
+    }
+
+    #[test]
+    fn a_tip_into_an_occupied_cell_is_refused() {
+        let game = |blockers: &[&[Dot]]| {
+            let config = GameConfig::new(7, 7).with_rotate(true).with_merge(false);
+            let mut game = Game::from_config(config, 0).unwrap();
+            let l = [Dot(2, 3), Dot(3, 3), Dot(4, 3), Dot(4, 4)];
+            for dots in std::iter::once(&l[..]).chain(blockers.iter().copied()) {
+                let cells = dots.iter().map(|d| (d.to_idx(0, 7) as Cell, 1));
+                let brick = Brick(cells.collect(), Lineage::default());
+                assert!(occupy(&mut game.grid, &brick));
+                game.push_brick(brick);
+            }
+            game
+        };
+        // a post on the bottom edge that the L stops on
+        let post = [Dot(2, 0), Dot(2, 1), Dot(2, 2)];
+        let mut free = game(&[&post]);
+        let before = free.bricks[0].clone();
+        let tipped = before.tipped(Down, &[Dot(2, 3).to_idx(0, 7)], 7).unwrap();
+        let (moved, _) = free.slide(Down, vec![]);
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].to, tipped.sorted_cells());
+
+        // off the column of any dot, so the contact stays the same
+        let target = Dot(4, 1);
+        assert!(tipped.dots(7).any(|d| d == target));
+        let mut blocked = game(&[&post, &[Dot(4, 0), target]]);
+        let (moved, _) = blocked.slide(Down, vec![]);
+        assert!(moved.is_empty());
+        assert_eq!(blocked.bricks[0].sorted_cells(), before.sorted_cells());
+    }
+
+    #[test]
+    fn bricks_stay_on_boards_past_the_old_limits() {
+        for (height, width) in [(15, 17), (16, 16), (20, 20), (32, 32), (255, 255)] {
+            let turns = if height == 255 { 40 } else { 200 };
+            for seed in 0..3 {
+                let mut game = Game::with_seed(height, width, seed);
+                let mut played = 0;
+                play_out(&mut game, turns, |game, _| {
+                    let mut cells = game.bricks.iter().flat_map(Brick::cells);
+                    assert!(cells.all(|c| c < game.grid.len()));
+                    for (dir, bricks) in &game.tray_bricks {
+                        let len = game.tray(*dir).len();
+                        assert!(bricks.iter().flat_map(Brick::cells).all(|c| c < len));
+                    }
+                    played += 1;
+                });
+                assert!(played > 0, "{height}x{width} played no move");
+            }
+        }
+    }
+
+    #[test]
+    fn a_tray_brick_larger_than_the_board_stays_out() {
+        let game = Game::from_config(GameConfig::new(6, 2).with_tray_depth(4), 0).unwrap();
+        let bar = [Dot(0, 0), Dot(1, 0), Dot(2, 0), Dot(3, 0)];
+        let wide = Brick::from_dots(&bar, 0, game.tray_width(Left));
+        assert!(game.entering(Left, &wide).is_none());
+        assert!(game.entering(Right, &wide).is_none());
+
+        let game = Game::from_config(GameConfig::new(2, 6).with_tray_depth(4), 0).unwrap();
+        let bar = bar.map(|Dot(x, y)| Dot(y, x));
+        let tall = Brick::from_dots(&bar, 0, game.tray_width(Down));
+        assert!(game.entering(Down, &tall).is_none());
+        assert!(game.entering(Up, &tall).is_none());
+
+        let short = Brick::from_dots(&bar[..2], 0, game.tray_width(Down));
+        let entered = game.entering(Up, &short).expect("fits the height");
+        assert_eq!(entered.sorted_cells(), vec![0, 6]);
+    }
+
+    #[test]
+    fn a_line_of_walls_never_clears() {
+        let mut layout = Layout::default().with_obstacle(Dot(0, 1), Obstacle::Wall);
+        for x in 0..5 {
+            layout = layout.with_obstacle(Dot(x, 0), Obstacle::Wall);
+        }
+        let layout = layout.with_brick((1..5).map(|x| (Dot(x, 1), 1)));
+        let config = GameConfig::new(8, 5).with_layout(layout);
+        let mut game = Game::from_config(config, 0).unwrap();
+        let report = game.step(Down).unwrap();
+        assert_eq!(report.cleared, vec![6, 7, 8, 9]);
+        assert!((0..6).all(|i| game[i] == Sq::Obstacle(Obstacle::Wall)));
+    }
+
+    #[test]
+    fn a_bag_deals_every_shape_once_a_cycle_past_blocked_trays() {
+        // a bar longer than the board fits in no tray
+        let mut shapes = ShapeSet::classic();
+        let mut bar = shapes.shapes[0].clone();
+        bar.name = "bar".into();
+        bar.cells = (0..9).map(|x| Dot(x, 0)).collect();
+        shapes.shapes.push(bar);
+        let bag = ShapeRandomizer::Bag(Bag::default());
+        let config = GameConfig::new(8, 8)
+            .with_shapes(shapes)
+            .with_randomizer(bag);
+        let mut game = Game::from_config(config, 3).unwrap();
+        let newest = |game: &Game, dir| game.tray_bricks[&dir].last().and_then(|b| b.1.shape);
+        let mut dealt: Vec<_> = Dir::iter().filter_map(|dir| newest(&game, dir)).collect();
+        let (mut redirected, mut stood_in) = (0, 0);
+        while let Some(&(want, tray)) = game.upcoming().front() {
+            let Ok((dir, _)) = game.gen_tray_brick() else {
+                break;
+            };
+            dealt.push(want);
+            redirected += usize::from(dir != tray);
+            // the dealt shape fit nowhere, and another stood in for it
+            stood_in += usize::from(newest(&game, dir) != Some(want));
+        }
+        let all: Vec<_> = game.config.shapes.all().collect();
+        assert!(redirected > 0 && stood_in > 0 && dealt.len() >= 2 * all.len());
+        for cycle in dealt.chunks_exact(all.len()) {
+            let mut cycle = cycle.to_vec();
+            cycle.sort_unstable();
+            assert_eq!(cycle, all);
+        }
+    }
+
+    #[test]
+    fn a_shape_set_with_nothing_to_deal_is_refused() {
+        let bad = |game: Result<_>| matches!(game, Err(Error::BadShapeSet(_)));
+        let empty = ShapeSet {
+            shapes: vec![],
+            ..ShapeSet::classic()
+        };
+        let config = GameConfig::new(5, 5).with_shapes(empty);
+        assert!(bad(Game::from_config(config, 0).map(drop)));
+        let mut game = Game::with_seed(5, 5, 0);
+        game.config.shapes.shapes.clear();
+        assert!(bad(Game::from_ron(&game.to_ron().unwrap()).map(drop)));
+        let replay = Replay::new(&game).to_ron().unwrap();
+        assert!(bad(Replay::from_ron(&replay).map(drop)));
+    }
+
+    #[test]
+    fn undoing_and_redoing_moves_comes_back_to_the_same_game() {
+        let seen = |game: &Game| {
+            let bricks: Vec<_> = game
+                .bricks
+                .iter()
+                .map(|b| (b.1, b.sorted_cells()))
+                .collect();
+            let upcoming = game.upcoming.clone();
+            (game.grid.clone(), bricks, game.score, game.turn, upcoming)
+        };
+        let config = GameConfig::new(10, 10).with_rotate(true).with_gravity(true);
+        let mut game = Game::from_config(config, 5).unwrap();
+        play_out(&mut game, 30, |_, _| {});
+        let mut line = game.clone();
+        for _ in 0..10 {
+            game.undo().unwrap();
+        }
+        assert_ne!(seen(&game), seen(&line));
+        for _ in 0..10 {
+            game.redo().unwrap();
+        }
+        assert_eq!(seen(&game), seen(&line));
+        // the rng came back too, so the moves after deal the same
+        for dir in [Up, Left, Down, Right] {
+            assert_eq!(game.step(dir), line.step(dir));
+            assert_eq!(seen(&game), seen(&line));
+        }
+    }
+
+    #[test]
+    fn a_saved_game_loads_and_plays_on_the_same() {
+        let bag = ShapeRandomizer::Bag(Bag::default());
+        let config = GameConfig::new(9, 9).with_rotate(true).with_randomizer(bag);
+        let mut game = Game::from_config(config, 11).unwrap();
+        play_out(&mut game, 20, |_, _| {});
+        let saves = [
+            Game::from_ron(&game.to_ron().unwrap()),
+            Game::from_json(&game.to_json().unwrap()),
+        ];
+        for loaded in saves {
+            let (mut loaded, mut game) = (loaded.unwrap(), game.clone());
+            let reports = |game: &mut Game| {
+                let mut reports = vec![];
+                play_out(game, 20, |_, report| reports.push(report.clone()));
+                reports
+            };
+            // the rng and randomizer were saved too, so the deals go on alike
+            let played = reports(&mut game);
+            assert!(!played.is_empty());
+            assert_eq!(reports(&mut loaded), played);
+            assert_eq!(loaded.upcoming, game.upcoming);
+            assert_eq!(loaded.grid, game.grid);
+            assert_eq!(loaded.score, game.score);
+        }
+    }
+
+    #[test]
+    fn an_equal_dot_in_the_way_merges_once_a_move() {
+        // a stack of three 1s, and a 1 under a 2
+        let layout = (0..3).fold(Layout::default(), |l, y| l.with_brick([(Dot(0, y), 1)]));
+        let layout = layout
+            .with_brick([(Dot(2, 0), 1)])
+            .with_brick([(Dot(2, 1), 2)]);
+        let config = GameConfig::new(5, 5).with_layout(layout);
+        let mut game = Game::from_config(config, 0).unwrap();
+        let (_, merged) = game.slide(Down, vec![]);
+        // the middle 1 joins the bottom one, which then holds a 2 the top 1
+        // only lands on
+        assert_eq!(merged, vec![0]);
+        let at = |x, y| game[Dot(x, y).to_idx(0, 5)];
+        assert_eq!(
+            [at(0, 0), at(0, 1), at(0, 2)],
+            [Sq::Dot(2), Sq::Dot(1), Sq::Empty]
+        );
+        assert_eq!([at(2, 0), at(2, 1)], [Sq::Dot(1), Sq::Dot(2)]);
+        assert_eq!(u64::from(game.score()), face_value(2));
+    }
+
+    #[test]
+    fn a_fragment_settles_into_a_cascade() {
+        // a full row with a dot on top, over a row short of that dot
+        let dots = |d: &[Dot]| d.iter().map(|&d| (d, 1)).collect::<Vec<_>>();
+        let layout = Layout::default()
+            .with_brick(dots(&[Dot(0, 1), Dot(1, 1), Dot(2, 1), Dot(2, 2)]))
+            .with_brick(dots(&[Dot(0, 0), Dot(1, 0)]));
+        let config = GameConfig::new(4, 3)
+            .with_merge(false)
+            .with_gravity(true)
+            .with_layout(layout);
+        let mut game = Game::from_config(config, 0).unwrap();
+        game.tray_bricks.values_mut().for_each(Vec::clear);
+        let report = game.step(Down).unwrap();
+        assert_eq!(report.cleared, vec![3, 4, 5]);
+        let [cascade] = &report.cascades[..] else {
+            panic!("one cascade, not {:?}", report.cascades);
+        };
+        assert_eq!(cascade.chain, 1);
+        let fell: Vec<_> = cascade
+            .fell
+            .iter()
+            .map(|m| (&m.from[..], &m.to[..]))
+            .collect();
+        // a step at a time, down to the row it completes
+        assert_eq!(fell, [(&[8][..], &[5][..]), (&[5], &[2])]);
+        assert_eq!(cascade.cleared, vec![0, 1, 2]);
+        assert!(game.iter().all(|sq| sq.is_empty()));
+    }
+
+    #[test]
+    fn a_brick_is_held_once_a_turn_and_swapped_back_later() {
+        let mut game = Game::with_seed(8, 8, 0);
+        // with bricks on the board, so the game goes on without the held one
+        play_out(&mut game, 6, |_, _| {});
+        let tray = Dir::iter()
+            .find(|d| !game.tray_bricks[d].is_empty())
+            .unwrap();
+        let id = game.tray_bricks[&tray].last().unwrap().1.id;
+        game.hold(tray).unwrap();
+        assert_eq!(game.held().map(|(d, b)| (*d, b.1.id)), Some((tray, id)));
+        assert!(game.tray_bricks[&tray].iter().all(|b| b.1.id != id));
+        assert_eq!(game.status(), GameStatus::Running);
+        assert!(!game.can_hold());
+        assert_eq!(game.hold(tray), Err(Error::HoldUsed));
+        let dir = Dir::iter().find(|&d| game.is_productive(d)).unwrap();
+        game.step(dir).unwrap();
+        assert!(game.can_hold());
+        // into the tray it is held for, in place of that tray's newest brick
+        let into = tray.opp();
+        let newest = game.tray_bricks[&into].last().map(|b| b.1.id);
+        game.hold(into).unwrap();
+        assert_eq!(game.tray_bricks[&into].last().map(|b| b.1.id), Some(id));
+        assert_eq!(game.held().map(|(_, b)| b.1.id), newest);
+    }
+
+    #[test]
+    fn games_end_won_at_the_goal_or_lost_for_a_reason() {
+        let mut game = Game::from_config(GameConfig::new(6, 6).with_goal(Some(10)), 0).unwrap();
+        play_out(&mut game, 100, |_, _| {});
+        assert_eq!(game.status(), GameStatus::Won);
+        assert!(game.score() >= 10);
+        // no brick fits a one cell tray
+        let config = GameConfig::new(1, 1).with_tray_depth(1);
+        let game = Game::from_config(config, 0).unwrap();
+        assert_eq!(game.status(), GameStatus::Lost(LossReason::TraysFull));
+        // nothing can move or come in on a board of walls
+        let mut walls = Layout::default();
+        for i in 0..9 {
+            walls = walls.with_obstacle(Dot::from_idx(i, 3), Obstacle::Wall);
+        }
+        let mut game = Game::from_config(GameConfig::new(3, 3).with_layout(walls), 0).unwrap();
+        assert_eq!(game.status(), GameStatus::Running);
+        let stuck = GameStatus::Lost(LossReason::Stuck);
+        assert_eq!(game.step(Up).map(|r| r.status), Ok(stuck));
+        assert_eq!(game.step(Up), Err(Error::GameOver(stuck)));
+        assert_eq!(game.hold(Up), Err(Error::GameOver(stuck)));
+    }
+
+    #[test]
+    fn the_bitboards_follow_every_move_and_undo() {
+        let walls = (0..6).fold(Layout::default(), |l, x| {
+            l.with_obstacle(Dot(x, 3), Obstacle::Wall)
+        });
+        let configs = [
+            GameConfig::new(9, 9),
+            GameConfig::new(12, 10)
+                .with_rotate(true)
+                .with_gravity(true)
+                .with_clear_rule(ClearRule::Shapes),
+            GameConfig::new(70, 9).with_merge(false).with_layout(walls),
+        ];
+        for (seed, config) in configs.into_iter().enumerate() {
+            let mut game = Game::from_config(config, seed as u64).unwrap();
+            let check = |game: &Game| {
+                let mut rebuilt = game.clone();
+                rebuilt.rebuild_boards();
+                assert_eq!(game.boards, rebuilt.boards);
+            };
+            play_out(&mut game, 200, |game, _| check(game));
+            while game.undo().is_ok() {
+                check(&game);
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_sweep_neighbours_dealt_as_the_same_shape() {
+        let def = |name: &str, cells: &[Dot]| ShapeDef {
+            name: name.into(),
+            cells: cells.to_vec(),
+            ..ShapeSet::classic().shapes[0].clone()
+        };
+        let domino = [Dot(0, 0), Dot(1, 0)];
+        let shapes = ShapeSet {
+            name: "sweep".into(),
+            shapes: vec![
+                def("a", &domino),
+                def("b", &domino),
+                def("dot", &[Dot(0, 0)]),
+                def("l", &[Dot(1, 0), Dot(0, 0), Dot(0, 1)]),
+            ],
+        };
+        let config = GameConfig::new(5, 5)
+            .with_clear_rule(ClearRule::Shapes)
+            .with_shapes(shapes);
+        let mut game = Game::from_config(config, 0).unwrap();
+        let brick = |dots: &[Dot], lineage| {
+            let mut brick = Brick::from_dots(dots, 0, 5);
+            brick.1 = lineage;
+            brick
+        };
+        let dealt = |shape: Option<u8>, id| Lineage::spawn(shape.map(Shape), BrickId(id));
+        game.bricks = vec![
+            brick(&[Dot(0, 0), Dot(1, 0)], dealt(Some(0), 0)),
+            // dealt as the same shape, turned
+            brick(&[Dot(0, 1), Dot(0, 2)], dealt(Some(0), 1)),
+            // the same cells, dealt as another shape
+            brick(&[Dot(1, 1), Dot(2, 1)], dealt(Some(1), 2)),
+            // not dealt, so alike by its cells
+            brick(&[Dot(2, 0), Dot(3, 0)], dealt(None, 3)),
+        ];
+        assert_eq!(game.swept_bricks(&[0, 1]), (vec![0], vec![1, 3]));
+
+        let fragment = dealt(Some(3), 4).fragment(BrickId(5));
+        game.bricks = vec![
+            brick(&[Dot(0, 0)], dealt(Some(2), 0)),
+            // a dot left of an L is a dot
+            brick(&[Dot(1, 0)], fragment),
+            // an L still whole is not
+            brick(&[Dot(0, 1), Dot(0, 2), Dot(1, 2)], dealt(Some(3), 6)),
+        ];
+        assert_eq!(game.swept_bricks(&[0]), (vec![0], vec![1]));
+    }
+
+    #[test]
+    fn zero_sizes_are_taken_as_one() {
+        for (height, width, depth) in [(7, 7, 0), (0, 7, 4), (7, 0, 4), (0, 0, 0)] {
+            let config = GameConfig::new(height, width).with_tray_depth(depth);
+            let mut game = Game::from_config(config, 0).unwrap();
+            assert_eq!(game.height(), height.max(1));
+            assert_eq!(game.width(), width.max(1));
+            assert_eq!(game.tray_depth(), depth.max(1));
+            play_out(&mut game, 20, |_, _| {});
+        }
+    }
+}
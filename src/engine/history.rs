@@ -0,0 +1,97 @@
+use rand_pcg::Pcg32;
+use std::collections::{HashMap, VecDeque};
+
+use super::{
+    game::{GameStatus, Sq},
+    randomizer::ShapeRandomizer,
+    shapes::{Brick, BrickId, Dir, Shape},
+};
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 64;
+
+/// Everything a move can change on a [`Game`](super::Game)
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub(super) grid: Vec<Sq>,
+    pub(super) tray: HashMap<Dir, Vec<Sq>>,
+    pub(super) bricks: Vec<Brick>,
+    pub(super) tray_bricks: HashMap<Dir, Vec<Brick>>,
+    pub(super) turn: u32,
+    pub(super) score: u32,
+    pub(super) streak: u32,
+    pub(super) status: GameStatus,
+    pub(super) rng: Pcg32,
+    pub(super) randomizer: ShapeRandomizer,
+    pub(super) upcoming: VecDeque<(Shape, Dir)>,
+    pub(super) held: Option<(Dir, Brick)>,
+    pub(super) held_on: Option<u32>,
+    pub(super) next_brick: BrickId,
+}
+
+/// Bounded undo/redo stacks of [`Snapshot`]s
+///
+/// The oldest snapshot is dropped once `limit` is reached, and any new move
+/// forgets what could have been redone.
+#[derive(Debug, Clone)]
+pub struct History {
+    past: VecDeque<Snapshot>,
+    future: Vec<Snapshot>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            past: VecDeque::new(),
+            future: Vec::new(),
+            limit,
+        }
+    }
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.past.len() > limit {
+            self.past.pop_front();
+        }
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.past.is_empty()
+    }
+    pub const fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+    /// Records the state before a new move
+    pub(super) fn record(&mut self, snapshot: Snapshot) {
+        self.future.clear();
+        self.push_past(snapshot);
+    }
+    fn push_past(&mut self, snapshot: Snapshot) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.past.len() == self.limit {
+            self.past.pop_front();
+        }
+        self.past.push_back(snapshot);
+    }
+    /// Swaps `current` for the latest recorded state
+    pub(super) fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let prev = self.past.pop_back()?;
+        self.future.push(current);
+        Some(prev)
+    }
+    /// Swaps `current` for the latest undone state
+    pub(super) fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.future.pop()?;
+        self.push_past(current);
+        Some(next)
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use super::shapes::Dot;
+
+/// A square of the board that is not part of any brick and never moves
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Obstacle {
+    /// Stays put through every clear
+    #[default]
+    Wall,
+    /// Goes with the line it completes
+    Rock,
+}
+
+/// What a board holds before the first move
+///
+/// Entries off the board, or on a square already taken by an earlier
+/// entry, are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub obstacles: Vec<(Dot, Obstacle)>,
+    /// Bricks as their dots and ranks; a rank of `0` counts as `1`
+    pub bricks: Vec<Vec<(Dot, u8)>>,
+}
+
+impl Layout {
+    pub fn with_obstacle(mut self, at: Dot, obstacle: Obstacle) -> Self {
+        self.obstacles.push((at, obstacle));
+        self
+    }
+    pub fn with_brick(mut self, dots: impl IntoIterator<Item = (Dot, u8)>) -> Self {
+        self.bricks.push(dots.into_iter().collect());
+        self
+    }
+}
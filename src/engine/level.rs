@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+pub const MAX_LEVEL: u8 = 15;
+
+/// How a game climbs from its start level
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Progression {
+    /// Stays at the start level
+    Fixed,
+    /// One level every so many points
+    Score(u32),
+    /// One level every so many turns
+    Turns(u32),
+}
+impl Default for Progression {
+    fn default() -> Self {
+        Self::Turns(30)
+    }
+}
+impl Progression {
+    /// The level reached from `start` after scoring `score` over `turns`
+    pub fn level(self, start: u8, score: u32, turns: u32) -> u8 {
+        let climbed = match self {
+            Self::Fixed => 0,
+            Self::Score(every) => score.checked_div(every).unwrap_or(0),
+            Self::Turns(every) => turns.checked_div(every).unwrap_or(0),
+        };
+        u32::from(start.max(1))
+            .saturating_add(climbed)
+            .min(MAX_LEVEL.into()) as u8
+    }
+}
+
+/// Bricks put into the trays after each move at `level`
+pub const fn spawns(level: u8) -> u8 {
+    match level {
+        0..=4 => 1,
+        5..=9 => 2,
+        _ => 3,
+    }
+}
+
+/// How many of the `depth` cells of a tray, counted from its start, new
+/// bricks may take at `level`
+pub const fn tray_space(level: u8, depth: u8) -> u8 {
+    let shrink = level.saturating_sub(1) / 6;
+    match depth.saturating_sub(shrink) {
+        space if space >= 2 => space,
+        _ if depth < 2 => depth,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_climb_to_more_spawns_and_less_tray_space() {
+        assert_eq!(Progression::Turns(30).level(1, 500, 59), 2);
+        assert_eq!(Progression::Score(100).level(3, 250, 0), 5);
+        assert_eq!(Progression::Fixed.level(0, 9000, 9000), 1);
+        assert_eq!(Progression::Turns(1).level(2, 0, 9000), MAX_LEVEL);
+        assert_eq!(Progression::Score(0).level(4, 9000, 0), 4);
+        let spawned: Vec<_> = [1, 4, 5, 9, 10, MAX_LEVEL].map(spawns).into();
+        assert_eq!(spawned, [1, 1, 2, 2, 3, 3]);
+        let space: Vec<_> = [1, 6, 7, 13, MAX_LEVEL].map(|l| tray_space(l, 4)).into();
+        assert_eq!(space, [4, 4, 3, 2, 2]);
+        // a tray too shallow to shrink keeps all of its depth
+        assert_eq!(tray_space(MAX_LEVEL, 1), 1);
+    }
+}
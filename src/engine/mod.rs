@@ -0,0 +1,25 @@
+//! Rules of the game, free of any rendering or ECS concerns.
+//!
+//! Everything in here builds without bevy, so bots, servers and test
+//! harnesses can drive [`Game`] directly. The bevy plugin in `avalanche`
+//! is only an adapter on top of it.
+pub use {
+    bitboard::*, config::*, error::*, game::*, history::*, layout::*, level::*, randomizer::*,
+    replay::*, report::*, save::*, scoring::*, shape_set::*, shapes::*,
+};
+
+mod bitboard;
+mod config;
+mod error;
+mod game;
+mod history;
+mod layout;
+mod level;
+pub mod polyomino;
+mod randomizer;
+mod replay;
+mod report;
+mod save;
+mod scoring;
+mod shape_set;
+mod shapes;
@@ -0,0 +1,137 @@
+//! Lists polyominoes, the shapes of `n` squares joined edge to edge.
+//!
+//! Shapes are kept as their cells, normalized to start at `(0, 0)` and
+//! sorted by row then column, so equal shapes have equal cell lists.
+use std::collections::BTreeSet;
+
+use super::shapes::{Brick, Dir, Dot};
+
+/// Shifts `cells` to touch both axes and sorts them
+pub fn normalized(cells: &[Dot]) -> Vec<Dot> {
+    let (x0, y0) = cells
+        .iter()
+        .fold((u8::MAX, u8::MAX), |(x, y), d| (x.min(d.0), y.min(d.1)));
+    let mut ret: Vec<_> = cells.iter().map(|d| Dot(d.0 - x0, d.1 - y0)).collect();
+    ret.sort_unstable_by_key(|d| (d.1, d.0));
+    ret
+}
+
+/// A quarter turn clockwise, with `y` counting up, as [`Brick::rotated`]
+/// turns bricks
+pub fn turned(cells: &[Dot]) -> Vec<Dot> {
+    let cells = normalized(cells);
+    let span = cells.iter().map(|d| d.0.max(d.1) + 1).max().unwrap_or(1);
+    Brick::from_dots(&cells, 0, span)
+        .rotated(Dir::Right, span)
+        .expect("a shape turns within a square of its longest side")
+        .footprint(span)
+}
+
+/// Flipped left to right
+pub fn mirrored(cells: &[Dot]) -> Vec<Dot> {
+    let right = cells.iter().map(|d| d.0).max().unwrap_or_default();
+    normalized(
+        &cells
+            .iter()
+            .map(|d| Dot(right - d.0, d.1))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// The distinct quarter turns of `cells`, from `cells` itself on
+pub fn rotations(cells: &[Dot]) -> Vec<Vec<Dot>> {
+    let mut ret: Vec<Vec<Dot>> = vec![];
+    let mut next = normalized(cells);
+    for _ in 0..4 {
+        if !ret.contains(&next) {
+            ret.push(next.clone());
+        }
+        next = turned(&next);
+    }
+    ret
+}
+
+/// The distinct rotations and reflections of `cells`, up to 8
+pub fn orientations(cells: &[Dot]) -> Vec<Vec<Dot>> {
+    let mut ret = rotations(cells);
+    for flipped in rotations(&mirrored(cells)) {
+        if !ret.contains(&flipped) {
+            ret.push(flipped);
+        }
+    }
+    ret
+}
+
+/// The orientation of `cells` that sorts first, the same for every
+/// rotation and reflection of it
+fn canonical(cells: &[Dot]) -> Vec<Dot> {
+    orientations(cells)
+        .into_iter()
+        .min_by_key(|c| c.iter().map(|d| (d.1, d.0)).collect::<Vec<_>>())
+        .unwrap_or_default()
+}
+
+/// Every free polyomino of `n` squares, each in its canonical orientation:
+/// 1, 1, 2, 5 and 12 of them for `n` from 1 to 5
+pub fn free(n: u8) -> Vec<Vec<Dot>> {
+    if n == 0 {
+        return vec![];
+    }
+    let mut found = BTreeSet::from([vec![(0, 0)]]);
+    for _ in 1..n {
+        let mut grown = BTreeSet::new();
+        for cells in &found {
+            // one step off the axes, so every neighbour has a place
+            let cells: Vec<_> = cells.iter().map(|&(y, x)| Dot(x + 1, y + 1)).collect();
+            for d in &cells {
+                for next in [
+                    Dot(d.0 - 1, d.1),
+                    Dot(d.0 + 1, d.1),
+                    Dot(d.0, d.1 - 1),
+                    Dot(d.0, d.1 + 1),
+                ] {
+                    if cells.contains(&next) {
+                        continue;
+                    }
+                    let mut bigger = cells.clone();
+                    bigger.push(next);
+                    grown.insert(canonical(&bigger).iter().map(|d| (d.1, d.0)).collect());
+                }
+            }
+        }
+        found = grown;
+    }
+    found
+        .into_iter()
+        .map(|cells: Vec<(u8, u8)>| cells.into_iter().map(|(y, x)| Dot(x, y)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shape_set::ShapeSet;
+    use super::*;
+
+    #[test]
+    fn counts_free_polyominoes() {
+        let counts: Vec<_> = (1..=5).map(|n| free(n).len()).collect();
+        assert_eq!(counts, [1, 1, 2, 5, 12]);
+        assert!(free(0).is_empty());
+    }
+
+    #[test]
+    fn counts_one_sided_polyominoes() {
+        let counts: Vec<_> = (2..=5)
+            .map(|n| ShapeSet::polyominoes(n).unwrap().shapes.len())
+            .collect();
+        assert_eq!(counts, [1, 2, 7, 18]);
+        assert_eq!(ShapeSet::polyominoes(5).unwrap().extent(), 5);
+    }
+
+    #[test]
+    fn counts_fixed_polyominoes() {
+        let fixed = |n| free(n).iter().map(|c| orientations(c).len()).sum::<usize>();
+        let counts: Vec<_> = (1..=5).map(fixed).collect();
+        assert_eq!(counts, [1, 2, 6, 19, 63]);
+    }
+}
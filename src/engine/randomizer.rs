@@ -0,0 +1,180 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::shape_set::ShapeSet;
+use super::shapes::Shape;
+
+/// Decides which shape comes next
+///
+/// All randomness is drawn from the `rng` passed in, so a seeded `rng` and
+/// the same calls give the same shapes.
+pub trait Randomizer {
+    /// Picks one of `pool`, which is never empty, out of the game's `shapes`
+    fn next_shape(&mut self, pool: &[Shape], shapes: &ShapeSet, rng: &mut dyn RngCore) -> Shape;
+}
+
+/// Every shape as likely as any other, however many ways it fits
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Uniform;
+impl Randomizer for Uniform {
+    fn next_shape(&mut self, pool: &[Shape], _: &ShapeSet, rng: &mut dyn RngCore) -> Shape {
+        pool[rng.gen_range(0..pool.len() as u32) as usize]
+    }
+}
+
+/// Deals every shape of the set once, in a random order, before dealing any
+/// again
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bag(Vec<Shape>);
+impl Randomizer for Bag {
+    fn next_shape(&mut self, pool: &[Shape], shapes: &ShapeSet, rng: &mut dyn RngCore) -> Shape {
+        if !self.0.iter().any(|s| pool.contains(s)) {
+            self.0 = shapes.all().collect();
+        }
+        let dealt: Vec<_> = (0..self.0.len())
+            .filter(|&i| pool.contains(&self.0[i]))
+            .collect();
+        let pick = dealt[rng.gen_range(0..dealt.len() as u32) as usize];
+        self.0.remove(pick)
+    }
+}
+
+/// Shapes drawn in proportion to their weight; shapes missing from the
+/// table never come unless nothing else fits. An empty table, the default,
+/// takes the weights of the shape set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Weighted(pub Vec<(Shape, u32)>);
+impl Randomizer for Weighted {
+    fn next_shape(&mut self, pool: &[Shape], shapes: &ShapeSet, rng: &mut dyn RngCore) -> Shape {
+        let table = match self.0.is_empty() {
+            true => shapes.weights(),
+            false => self.0.clone(),
+        };
+        let weights: Vec<_> = table.iter().filter(|(s, _)| pool.contains(s)).collect();
+        let total: u32 = weights.iter().map(|(_, w)| w).sum();
+        if total == 0 {
+            return Uniform.next_shape(pool, shapes, rng);
+        }
+        let mut roll = rng.gen_range(0..total);
+        for &&(shape, weight) in weights.iter() {
+            if roll < weight {
+                return shape;
+            }
+            roll -= weight;
+        }
+        pool[0]
+    }
+}
+
+/// Rerolls, up to `tries` times, a shape among the last `depth` dealt. With
+/// a `depth` of at least one, the last shape never comes twice in a row
+/// while the pool holds another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AntiRepeat {
+    pub depth: u8,
+    pub tries: u8,
+    #[serde(default)]
+    recent: VecDeque<Shape>,
+}
+impl AntiRepeat {
+    pub const fn new(depth: u8, tries: u8) -> Self {
+        Self {
+            depth,
+            tries,
+            recent: VecDeque::new(),
+        }
+    }
+}
+impl Default for AntiRepeat {
+    fn default() -> Self {
+        Self::new(4, 4)
+    }
+}
+impl Randomizer for AntiRepeat {
+    fn next_shape(&mut self, pool: &[Shape], shapes: &ShapeSet, rng: &mut dyn RngCore) -> Shape {
+        let mut shape = Uniform.next_shape(pool, shapes, rng);
+        for _ in 1..self.tries {
+            if !self.recent.contains(&shape) {
+                break;
+            }
+            shape = Uniform.next_shape(pool, shapes, rng);
+        }
+        if self.recent.back() == Some(&shape) {
+            let others: Vec<_> = pool.iter().copied().filter(|&s| s != shape).collect();
+            if !others.is_empty() {
+                shape = Uniform.next_shape(&others, shapes, rng);
+            }
+        }
+        self.recent.push_back(shape);
+        while self.recent.len() > self.depth as usize {
+            self.recent.pop_front();
+        }
+        shape
+    }
+}
+
+/// The [`Randomizer`] a game deals its shapes with; the one in the
+/// [`GameConfig`](super::GameConfig) is where every game starts from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShapeRandomizer {
+    Uniform(Uniform),
+    Bag(Bag),
+    Weighted(Weighted),
+    AntiRepeat(AntiRepeat),
+}
+impl Default for ShapeRandomizer {
+    fn default() -> Self {
+        Self::Uniform(Uniform)
+    }
+}
+impl Randomizer for ShapeRandomizer {
+    fn next_shape(&mut self, pool: &[Shape], shapes: &ShapeSet, rng: &mut dyn RngCore) -> Shape {
+        match self {
+            Self::Uniform(r) => r.next_shape(pool, shapes, rng),
+            Self::Bag(r) => r.next_shape(pool, shapes, rng),
+            Self::Weighted(r) => r.next_shape(pool, shapes, rng),
+            Self::AntiRepeat(r) => r.next_shape(pool, shapes, rng),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    /// `n` shapes of the classic set from `randomizer`, out of `pool`
+    fn deal(mut randomizer: impl Randomizer, pool: &[Shape], n: usize, seed: u64) -> Vec<Shape> {
+        let (shapes, mut rng) = (ShapeSet::classic(), Pcg32::seed_from_u64(seed));
+        (0..n)
+            .map(|_| randomizer.next_shape(pool, &shapes, &mut rng))
+            .collect()
+    }
+
+    #[test]
+    fn a_bag_deals_every_shape_once_a_cycle() {
+        let all: Vec<_> = ShapeSet::classic().all().collect();
+        for seed in 0..20 {
+            for cycle in deal(Bag::default(), &all, 10 * all.len(), seed).chunks(all.len()) {
+                let mut cycle = cycle.to_vec();
+                cycle.sort_unstable();
+                assert_eq!(cycle, all);
+            }
+        }
+    }
+
+    #[test]
+    fn anti_repeat_never_deals_a_shape_twice_in_a_row() {
+        let all: Vec<_> = ShapeSet::classic().all().collect();
+        for (pool, tries) in [(&all[..], 1), (&all[..], 4), (&all[..2], 1)] {
+            for seed in 0..20 {
+                let dealt = deal(AntiRepeat::new(1, tries), pool, 200, seed);
+                assert!(dealt.windows(2).all(|w| w[0] != w[1]));
+            }
+        }
+        // with nothing else to deal, the one shape comes again
+        assert_eq!(deal(AntiRepeat::default(), &all[..1], 3, 0), [all[0]; 3]);
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::{
+    config::GameConfig,
+    error::{Error, Result},
+    game::Game,
+    report::MoveReport,
+    shapes::Dir,
+};
+
+/// Bumped whenever a change to [`Replay`] breaks older replay files
+pub const REPLAY_VERSION: u32 = 1;
+
+/// What the player can do to a [`Game`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// [`Game::step`]
+    Move(Dir),
+    /// [`Game::hold`]
+    Hold(Dir),
+}
+impl Action {
+    /// Does it to `game`; only moves report anything
+    pub fn apply(self, game: &mut Game) -> Result<Option<MoveReport>> {
+        match self {
+            Self::Move(dir) => game.step(dir).map(Some),
+            Self::Hold(dir) => game.hold(dir).map(|()| None),
+        }
+    }
+}
+
+/// An action and when it was taken, in milliseconds since the game started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayMove(pub Action, pub u32);
+
+/// Everything needed to play a game again: the board, the seed and the moves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub config: GameConfig,
+    pub seed: u64,
+    pub moves: Vec<ReplayMove>,
+    /// Moves taken back, kept until the next new move
+    #[serde(skip)]
+    undone: Vec<ReplayMove>,
+}
+
+impl Replay {
+    /// Starts recording `game`, which should not have been played yet
+    pub fn new(game: &Game) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            config: game.config().clone(),
+            seed: game.seed(),
+            moves: vec![],
+            undone: vec![],
+        }
+    }
+    pub fn record(&mut self, action: Action, at: Duration) {
+        self.undone.clear();
+        let at = u32::try_from(at.as_millis()).unwrap_or(u32::MAX);
+        self.moves.push(ReplayMove(action, at));
+    }
+    /// Follows [`Game::undo`]
+    pub fn undo(&mut self) {
+        self.undone.extend(self.moves.pop());
+    }
+    /// Follows [`Game::redo`]
+    pub fn redo(&mut self) {
+        self.moves.extend(self.undone.pop());
+    }
+    /// The game as it was before the first move
+    pub fn game(&self) -> Result<Game> {
+        Game::from_config(self.config.clone(), self.seed)
+    }
+    /// Plays every move, stopping at the first one the game refuses
+    pub fn run(&self) -> Result<Game> {
+        let mut game = self.game()?;
+        for &ReplayMove(action, _) in &self.moves {
+            action.apply(&mut game)?;
+        }
+        Ok(game)
+    }
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.moves.last().map_or(0, |m| m.1.into()))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::to_string(self).map_err(|e| Error::Format(e.to_string()))
+    }
+    pub fn from_ron(s: &str) -> Result<Self> {
+        let replay: Self = ron::from_str(s).map_err(|e| Error::Format(e.to_string()))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(Error::UnsupportedVersion(replay.version));
+        }
+        replay.config.shapes.validate()?;
+        Ok(replay)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        std::fs::write(path, self.to_ron()?).map_err(|e| Error::Io(e.to_string()))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?)
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    game::GameStatus,
+    scoring::ScoreBreakdown,
+    shapes::{BrickId, Dir},
+};
+
+/// A brick that slid on the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrickMove {
+    pub id: BrickId,
+    pub from: Vec<usize>,
+    pub to: Vec<usize>,
+}
+
+/// A brick that left the tray on the `dir` side for the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrayEntry {
+    pub id: BrickId,
+    pub dir: Dir,
+    /// Cells it took in the tray
+    pub from: Vec<usize>,
+    /// Cells it takes on the board
+    pub to: Vec<usize>,
+}
+
+/// One round of a cascade: the fragments of the last clear fell towards the
+/// played direction, which may have filled and cleared more lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CascadeStep {
+    /// `1` for the fall after the first clear, and so on
+    pub chain: u32,
+    pub fell: Vec<BrickMove>,
+    pub merged: Vec<usize>,
+    pub cleared: Vec<usize>,
+}
+
+/// What one call to `Game::play` changed, so a front end can animate the
+/// difference instead of scanning the whole board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveReport {
+    pub dir: Dir,
+    /// Bricks that moved, the rest stayed put
+    pub moved: Vec<BrickMove>,
+    /// Board cells whose dot took in an equal one and went up a rank
+    pub merged: Vec<usize>,
+    pub entered: Vec<TrayEntry>,
+    /// Board cells emptied by the first line clear of the move
+    pub cleared: Vec<usize>,
+    /// Falls and further clears after the first, in order
+    pub cascades: Vec<CascadeStep>,
+    /// The bricks added to the trays after the move, and their tray cells
+    pub spawned: Vec<(Dir, Vec<usize>)>,
+    pub score_delta: u32,
+    /// How `score_delta` adds up
+    pub points: ScoreBreakdown,
+    pub status: GameStatus,
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{Error, Result},
+    game::Game,
+};
+
+/// Bumped whenever a change to [`Game`] breaks older save files
+pub const SAVE_VERSION: u32 = 1;
+
+/// What is written to disk: a [`Game`] tagged with the format version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub game: Game,
+}
+
+/// Only reads the version, so an old or newer file is reported as such
+/// instead of as a parse error.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+const fn check_version(version: u32) -> Result<()> {
+    match version {
+        SAVE_VERSION => Ok(()),
+        _ => Err(Error::UnsupportedVersion(version)),
+    }
+}
+
+impl SaveFile {
+    /// The saved game, ready to play on
+    fn resume(self) -> Result<Game> {
+        let mut game = self.game;
+        game.config().shapes.validate()?;
+        game.rebuild_boards();
+        Ok(game)
+    }
+}
+
+impl Game {
+    pub fn to_ron(&self) -> Result<String> {
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            game: self.clone(),
+        };
+        ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())
+            .map_err(|e| Error::Format(e.to_string()))
+    }
+    pub fn from_ron(s: &str) -> Result<Self> {
+        let SaveHeader { version } = ron::Options::default()
+            .without_recursion_limit()
+            .from_str(s)
+            .map_err(|e| Error::Format(e.to_string()))?;
+        check_version(version)?;
+        let save: SaveFile = ron::from_str(s).map_err(|e| Error::Format(e.to_string()))?;
+        save.resume()
+    }
+    pub fn to_json(&self) -> Result<String> {
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            game: self.clone(),
+        };
+        serde_json::to_string(&save).map_err(|e| Error::Format(e.to_string()))
+    }
+    pub fn from_json(s: &str) -> Result<Self> {
+        let SaveHeader { version } =
+            serde_json::from_str(s).map_err(|e| Error::Format(e.to_string()))?;
+        check_version(version)?;
+        let save: SaveFile = serde_json::from_str(s).map_err(|e| Error::Format(e.to_string()))?;
+        save.resume()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        std::fs::write(path, self.to_ron()?).map_err(|e| Error::Io(e.to_string()))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?)
+    }
+}
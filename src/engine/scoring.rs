@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// One round of full lines emptied in a move: the first right after the
+/// slide, then one per cascade
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineClear {
+    pub rows: u32,
+    pub cols: u32,
+    /// What the [`ClearRule`](super::ClearRule) awards for what it took
+    pub points: u32,
+    /// `0` for the clear right after the slide, `n` for the nth cascade
+    pub chain: u32,
+}
+impl LineClear {
+    pub const fn lines(&self) -> u32 {
+        self.rows + self.cols
+    }
+}
+
+/// Where the points of one move came from, for the HUD to show
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Face values of the dots merged on the way
+    pub merges: u32,
+    /// Awarded by the [`ClearRule`](super::ClearRule) for the lines
+    pub lines: u32,
+    pub multi_line: u32,
+    pub cross: u32,
+    /// Extra points for clears made by cascades
+    pub cascade: u32,
+    /// Multiplier for clearing on consecutive turns
+    pub combo: u32,
+    pub total: u32,
+}
+impl ScoreBreakdown {
+    /// Points of the clears, before the combo multiplier
+    pub const fn clears(&self) -> u32 {
+        self.lines + self.multi_line + self.cross + self.cascade
+    }
+}
+
+impl Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.combo {
+            0 | 1 => write!(f, "+{}", self.total),
+            combo => write!(f, "+{} (x{combo} combo)", self.total),
+        }
+    }
+}
+
+/// Turns the line clears of a move into points
+pub trait ScoringRule {
+    /// Scores `clears`, made on the `streak`th turn in a row to clear
+    /// anything. `merges` and `total` are left to the caller.
+    fn score(&self, clears: &[LineClear], streak: u32) -> ScoreBreakdown;
+}
+
+/// The clear rule's points and nothing more
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Flat;
+impl ScoringRule for Flat {
+    fn score(&self, clears: &[LineClear], _: u32) -> ScoreBreakdown {
+        ScoreBreakdown {
+            lines: clears.iter().map(|c| c.points).sum(),
+            combo: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/// Tetris-like scoring
+///
+/// Clearing several lines at once, or a row and a column together, earns a
+/// bonus. The nth cascade of a move scores its clear `n + 1` times, and
+/// clearing on consecutive turns multiplies the whole move by the streak.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Classic;
+impl ScoringRule for Classic {
+    fn score(&self, clears: &[LineClear], streak: u32) -> ScoreBreakdown {
+        let mut ret = ScoreBreakdown {
+            combo: streak.max(1),
+            ..Default::default()
+        };
+        for clear in clears {
+            let lines = clear.lines();
+            let multi_line = lines * lines.saturating_sub(1);
+            let cross = 2 * clear.rows * clear.cols;
+            ret.lines += clear.points;
+            ret.multi_line += multi_line;
+            ret.cross += cross;
+            ret.cascade += (clear.points + multi_line + cross) * clear.chain;
+        }
+        ret
+    }
+}
+
+/// The [`ScoringRule`] a game mode plays with
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scoring {
+    Flat,
+    #[default]
+    Classic,
+}
+impl ScoringRule for Scoring {
+    fn score(&self, clears: &[LineClear], streak: u32) -> ScoreBreakdown {
+        match self {
+            Self::Flat => Flat.score(clears, streak),
+            Self::Classic => Classic.score(clears, streak),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn clear(rows: u32, cols: u32, chain: u32) -> LineClear {
+        LineClear {
+            rows,
+            cols,
+            points: rows + cols,
+            chain,
+        }
+    }
+
+    #[test]
+    fn classic_adds_bonuses_for_lines_crosses_cascades_and_streaks() {
+        let single = Classic.score(&[clear(1, 0, 0)], 0);
+        assert_eq!((single.clears(), single.combo), (1, 1));
+        // a row and a column at once, then two rows in the first cascade,
+        // on the third turn in a row to clear
+        let points = Classic.score(&[clear(1, 1, 0), clear(2, 0, 1)], 3);
+        let expected = ScoreBreakdown {
+            lines: 2 + 2,
+            multi_line: 2 + 2,
+            cross: 2,
+            cascade: 2 + 2,
+            combo: 3,
+            ..Default::default()
+        };
+        assert_eq!(points, expected);
+        assert_eq!(points.clears(), 14);
+        let total = ScoreBreakdown {
+            total: 42,
+            ..points
+        };
+        assert_eq!(total.to_string(), "+42 (x3 combo)");
+    }
+}
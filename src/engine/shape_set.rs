@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use strum::IntoEnumIterator;
+
+use super::error::{Error, Result};
+use super::polyomino;
+use super::shapes::{Brick, Dir, Dot, Shape};
+
+/// One shape of a [`ShapeSet`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShapeDef {
+    pub name: String,
+    /// Its cells pointing `Up`, from the top left of its bounding box
+    pub cells: Vec<Dot>,
+    /// The orientations it may spawn in; every distinct one when `None`
+    #[serde(default)]
+    pub rotations: Option<Vec<Dir>>,
+    /// RGB the front end draws it in
+    #[serde(default = "white")]
+    pub color: (u8, u8, u8),
+    /// Odds of spawning under a [`Weighted`](super::Weighted) randomizer
+    /// without a table of its own
+    #[serde(default = "one")]
+    pub weight: u32,
+    /// Highest level it spawns at; every level when `None`
+    #[serde(default)]
+    pub last_level: Option<u8>,
+}
+
+impl ShapeDef {
+    /// The orientations it spawns in: its cells turned a quarter clockwise
+    /// per [`Dir::turn`] from `Up`, up to the first turn that repeats one,
+    /// and kept to its `rotations` if it lists any
+    pub fn orientations(&self) -> impl Iterator<Item = (Dir, Vec<Dot>)> + '_ {
+        Dir::iter()
+            .zip(polyomino::rotations(&self.cells))
+            .filter(|(dir, _)| self.rotations.as_ref().is_none_or(|r| r.contains(dir)))
+    }
+}
+const fn white() -> (u8, u8, u8) {
+    (255, 255, 255)
+}
+const fn one() -> u32 {
+    1
+}
+
+/// The shapes a game is played with, as shipped in a `.shapes.ron` or
+/// `.shapes.json` file
+///
+/// A [`Shape`] is a place in [`ShapeSet::shapes`], so a game only makes
+/// sense with the set it was started with; the set travels in its
+/// [`GameConfig`](super::GameConfig).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShapeSet {
+    pub name: String,
+    pub shapes: Vec<ShapeDef>,
+}
+
+const CLASSIC: &str = include_str!("../../assets/shapes/classic.shapes.ron");
+
+/// Colours generated sets cycle through
+const PALETTE: [(u8, u8, u8); 6] = [
+    (50, 205, 50),
+    (0, 128, 128),
+    (127, 255, 212),
+    (255, 99, 71),
+    (128, 0, 0),
+    (128, 0, 128),
+];
+
+/// Most squares [`ShapeSet::polyominoes`] generates shapes of: the 196
+/// one-sided heptominoes fit a set, the 704 octominoes are past its 256
+pub const LARGEST_POLYOMINO: u8 = 7;
+
+/// `A` to `Z`, then `AA`, `AB` and on, for the `i`th generated shape
+fn letters(i: usize) -> String {
+    let mut ret = vec![];
+    let mut i = i + 1;
+    while i > 0 {
+        i -= 1;
+        ret.push(char::from(b'A' + (i % 26) as u8));
+        i /= 26;
+    }
+    ret.iter().rev().collect()
+}
+
+/// The shape sets a game can be started with by name
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShapePool {
+    Trominoes,
+    /// The [classic](ShapeSet::classic) set
+    #[default]
+    Tetrominoes,
+    Pentominoes,
+}
+
+impl ShapePool {
+    pub fn shapes(self) -> ShapeSet {
+        match self {
+            Self::Trominoes => ShapeSet::polyominoes(3),
+            Self::Tetrominoes => Ok(ShapeSet::classic()),
+            Self::Pentominoes => ShapeSet::polyominoes(5),
+        }
+        .expect("trominoes and pentominoes are generated")
+    }
+}
+
+impl Default for ShapeSet {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl ShapeSet {
+    /// The six tetromino-like shapes of `assets/shapes/classic.shapes.ron`
+    pub fn classic() -> Self {
+        static SET: OnceLock<ShapeSet> = OnceLock::new();
+        SET.get_or_init(|| Self::from_ron(CLASSIC).expect("the classic shape set is valid"))
+            .clone()
+    }
+    /// Every one-sided polyomino of `n` squares with each of its distinct
+    /// quarter turns, named by letter with a `'` for the mirrored twin; past
+    /// `Z`, names go on with `AA`, `AB` and so on. `n` is 1 to
+    /// [`LARGEST_POLYOMINO`].
+    pub fn polyominoes(n: u8) -> Result<Self> {
+        if !(1..=LARGEST_POLYOMINO).contains(&n) {
+            return Err(Error::BadShapeSet(format!(
+                "polyominoes of {n} squares, not 1 to {LARGEST_POLYOMINO}"
+            )));
+        }
+        let free = polyomino::free(n);
+        let mut shapes = vec![];
+        for (i, cells) in free.iter().enumerate() {
+            let letter = letters(i);
+            let flipped = polyomino::mirrored(cells);
+            let mut sides = vec![(letter.to_string(), cells.clone())];
+            if !polyomino::rotations(cells).contains(&flipped) {
+                sides.push((format!("{letter}'"), flipped));
+            }
+            for (name, cells) in sides {
+                shapes.push(ShapeDef {
+                    name,
+                    cells,
+                    rotations: None,
+                    color: PALETTE[shapes.len() % PALETTE.len()],
+                    weight: one(),
+                    last_level: None,
+                });
+            }
+        }
+        Ok(Self {
+            name: format!("{n}-ominoes"),
+            shapes,
+        })
+    }
+    pub fn from_ron(s: &str) -> Result<Self> {
+        let set: Self = ron::from_str(s).map_err(|e| Error::BadShapeSet(e.to_string()))?;
+        set.validate()?;
+        Ok(set)
+    }
+    pub fn from_json(s: &str) -> Result<Self> {
+        let set: Self = serde_json::from_str(s).map_err(|e| Error::BadShapeSet(e.to_string()))?;
+        set.validate()?;
+        Ok(set)
+    }
+    /// Checks there is a shape to deal, at most 256 of them, named apart,
+    /// and that each has distinct cells and an orientation to spawn in
+    pub fn validate(&self) -> Result<()> {
+        let bad = |why: String| Err(Error::BadShapeSet(why));
+        if self.shapes.is_empty() || self.shapes.len() > 256 {
+            return bad(format!("{} shapes, not 1 to 256", self.shapes.len()));
+        }
+        let mut names: Vec<_> = self.shapes.iter().map(|s| &s.name).collect();
+        names.sort_unstable();
+        if let Some(twice) = names.windows(2).find(|w| w[0] == w[1]) {
+            return bad(format!("{} names two shapes", twice[0]));
+        }
+        for shape in &self.shapes {
+            let mut sorted = shape.cells.clone();
+            sorted.sort_unstable_by_key(|d| (d.1, d.0));
+            sorted.dedup();
+            if shape.cells.is_empty() || sorted.len() != shape.cells.len() {
+                return bad(format!("{} has no or repeated cells", shape.name));
+            }
+            if shape.orientations().next().is_none() {
+                return bad(format!("{} has no orientation to spawn in", shape.name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, shape: Shape) -> Option<&ShapeDef> {
+        self.shapes.get(shape.0 as usize)
+    }
+    pub fn name(&self, shape: Shape) -> &str {
+        self.get(shape).map_or("?", |s| s.name.as_str())
+    }
+    /// Every shape, in the order of the set
+    pub fn all(&self) -> impl Iterator<Item = Shape> {
+        (0..self.shapes.len()).map(|i| Shape(i as u8))
+    }
+    /// Every shape with every orientation it spawns in
+    pub fn variants(&self) -> impl Iterator<Item = (Shape, Dir)> + '_ {
+        self.all()
+            .zip(&self.shapes)
+            .flat_map(|(shape, def)| def.orientations().map(move |(dir, _)| (shape, dir)))
+    }
+    /// `shape` pointing to `dir`, its top left at the index `orig` of a grid
+    /// of `width`; `None` if the shape does not spawn that way
+    pub fn brick(&self, shape: Shape, dir: Dir, orig: usize, width: u8) -> Option<Brick> {
+        let (_, dots) = self.get(shape)?.orientations().find(|(d, _)| *d == dir)?;
+        let mut brick = Brick::from_dots(&dots, orig, width);
+        brick.1.shape = Some(shape);
+        Some(brick)
+    }
+    /// Shapes that can spawn at `level`; all of them, if every one dropped out
+    pub fn pool(&self, level: u8) -> Vec<Shape> {
+        let pool: Vec<_> = self
+            .all()
+            .zip(&self.shapes)
+            .filter(|(_, def)| def.last_level.is_none_or(|last| level <= last))
+            .map(|(shape, _)| shape)
+            .collect();
+        match pool.is_empty() {
+            true => self.all().collect(),
+            false => pool,
+        }
+    }
+    /// Longest side of any shape, so a front end can size a slot that fits
+    /// every shape in every orientation
+    pub fn extent(&self) -> u8 {
+        self.shapes
+            .iter()
+            .flat_map(|s| polyomino::normalized(&s.cells))
+            .map(|d| d.0.max(d.1) + 1)
+            .max()
+            .unwrap_or(1)
+    }
+    pub fn weights(&self) -> Vec<(Shape, u32)> {
+        self.all()
+            .zip(self.shapes.iter().map(|s| s.weight))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = r#"ShapeSet(
+        name: "Limited",
+        shapes: [
+            (name: "L", cells: [(1, 0), (0, 0), (0, 1), (0, 2)], rotations: Some([Up, Down])),
+            (name: "O", cells: [(0, 0), (1, 0), (1, 1), (0, 1)]),
+        ],
+    )"#;
+
+    #[test]
+    fn rotations_limit_the_orientations() {
+        let set = ShapeSet::from_ron(PACK).unwrap();
+        let variants: Vec<_> = set.variants().collect();
+        assert_eq!(
+            variants,
+            [
+                (Shape(0), Dir::Up),
+                (Shape(0), Dir::Down),
+                (Shape(1), Dir::Up)
+            ]
+        );
+        assert!(set.brick(Shape(0), Dir::Right, 0, 5).is_none());
+    }
+
+    #[test]
+    fn polyominoes_are_generated_up_to_a_full_set() {
+        assert!(ShapeSet::polyominoes(0).is_err());
+        assert!(ShapeSet::polyominoes(LARGEST_POLYOMINO + 1).is_err());
+        let set = ShapeSet::polyominoes(LARGEST_POLYOMINO).unwrap();
+        assert_eq!(set.shapes.len(), 196);
+        assert!(set.validate().is_ok());
+        assert_eq!([25, 26, 27, 702].map(letters), ["Z", "AA", "AB", "AAA"]);
+    }
+
+    #[test]
+    fn a_shape_without_an_orientation_is_rejected() {
+        assert!(ShapeSet::classic().validate().is_ok());
+        let o = "(0, 1)]),";
+        let pack = PACK.replace(o, "(0, 1)], rotations: Some([Right])),");
+        assert!(ShapeSet::from_ron(&pack).is_err());
+    }
+}
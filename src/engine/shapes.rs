@@ -0,0 +1,503 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+/// A shape of a [`ShapeSet`](super::ShapeSet), by its place in the set
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(
+    Default, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Shape(pub u8);
+use std::collections::HashMap;
+
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, EnumIter, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dir {
+    #[default]
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Dir {
+    pub const fn opp(&self) -> Self {
+        match self {
+            Up => Down,
+            Down => Up,
+            Left => Right,
+            Right => Left,
+        }
+    }
+
+    pub const fn turn(&self) -> Self {
+        match self {
+            Up => Right,
+            Right => Down,
+            Down => Left,
+            Left => Up,
+        }
+    }
+
+    /// Quarter turns clockwise from `Up`
+    pub const fn quarter_turns(&self) -> u8 {
+        match self {
+            Up => 0,
+            Right => 1,
+            Down => 2,
+            Left => 3,
+        }
+    }
+
+    pub const fn is_top_right(&self) -> bool {
+        match self {
+            Right | Up => true,
+            Left | Down => false,
+        }
+    }
+    pub fn if_tr<T>(&self, y: T, n: T) -> T {
+        if self.is_top_right() {
+            y
+        } else {
+            n
+        }
+    }
+    pub fn if_h<T>(&self, y: T, n: T) -> T {
+        if self.is_horizontal() {
+            y
+        } else {
+            n
+        }
+    }
+    pub const fn is_horizontal(self) -> bool {
+        match self {
+            Right | Left => true,
+            Up | Down => false,
+        }
+    }
+}
+use Dir::*;
+
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct Dot(pub u8, pub u8);
+impl Dot {
+    /// Index of the dot on a grid of `width`, offset by the index `orig`
+    pub const fn to_idx(self, orig: usize, width: u8) -> usize {
+        self.0 as usize + self.1 as usize * width as usize + orig
+    }
+    pub const fn from_idx(idx: usize, width: u8) -> Self {
+        let width = width as usize;
+        Self((idx % width) as u8, (idx / width) as u8)
+    }
+    /// Splits `ids` into groups of dots joined edge to edge, merging the
+    /// groups of neighbours in a union-find. Dots keep their order within a
+    /// group; the group of the last dot comes first, then the group of the
+    /// last dot not yet grouped, and so on.
+    pub fn group_connected(ids: &[Self]) -> Vec<Vec<Self>> {
+        if ids.is_empty() {
+            return vec![vec![]];
+        }
+        let at: HashMap<Self, usize> = ids.iter().enumerate().map(|(i, &d)| (d, i)).collect();
+        let mut parent: Vec<_> = (0..ids.len()).collect();
+        let root = |parent: &mut Vec<usize>, mut i: usize| {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        };
+        for (i, d) in ids.iter().enumerate() {
+            let right = d.0.checked_add(1).map(|x| Self(x, d.1));
+            let above = d.1.checked_add(1).map(|y| Self(d.0, y));
+            for j in [right, above]
+                .into_iter()
+                .flatten()
+                .filter_map(|n| at.get(&n))
+            {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, *j));
+                parent[a] = b;
+            }
+        }
+        let mut group_of = HashMap::new();
+        let mut res: Vec<Vec<Self>> = vec![];
+        for i in (0..ids.len()).rev() {
+            let r = root(&mut parent, i);
+            let g = *group_of.entry(r).or_insert_with(|| {
+                res.push(vec![]);
+                res.len() - 1
+            });
+            res[g].push(ids[i]);
+        }
+        res.iter_mut().for_each(|g| g.reverse());
+        res
+    }
+}
+
+/// Index of a square in a grid or tray, row major
+///
+/// Wide enough for any grid of `u8` sides, up to 255 x 255.
+pub type Cell = u16;
+
+/// A brick's number, unique within its game and kept through slides, tray
+/// entry, tips and holds
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(
+    Debug, Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct BrickId(pub u32);
+impl BrickId {
+    /// Hands out this id and moves on to the next
+    pub const fn issue(&mut self) -> Self {
+        let id = *self;
+        self.0 = self.0.wrapping_add(1);
+        id
+    }
+}
+
+/// Where a brick comes from: the shape it spawned as, if it spawned as
+/// one, and the bricks it was split off
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub shape: Option<Shape>,
+    pub id: BrickId,
+    /// The brick it was split off, if it is a fragment
+    pub parent: Option<BrickId>,
+    /// The spawn it descends from, shared by every fragment split off it
+    pub root: BrickId,
+}
+impl Lineage {
+    pub const fn spawn(shape: Option<Shape>, id: BrickId) -> Self {
+        Self {
+            shape,
+            id,
+            parent: None,
+            root: id,
+        }
+    }
+    /// The lineage of a piece split off this brick as a brick of its own
+    pub const fn fragment(self, id: BrickId) -> Self {
+        Self {
+            id,
+            parent: Some(self.id),
+            ..self
+        }
+    }
+}
+
+/// A HashMap of dots and their values, and the [`Lineage`] of the brick
+///
+/// Dots are keyed by their index in the grid the brick currently lives in,
+/// so every geometric helper needs that grid's `width`.
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::Inspectable))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Brick(pub HashMap<Cell, u8>, pub Lineage);
+
+impl Brick {
+    /// A brick of empty dots on `dots`, shifted to start at the index `orig`
+    /// of a grid of `width`
+    pub fn from_dots(dots: &[Dot], orig: usize, width: u8) -> Self {
+        Self(
+            dots.iter()
+                .map(|d| (d.to_idx(orig, width) as Cell, 0))
+                .collect(),
+            Lineage::default(),
+        )
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.keys().map(|&k| k as usize)
+    }
+    pub fn sorted_cells(&self) -> Vec<usize> {
+        let mut cells: Vec<_> = self.cells().collect();
+        cells.sort_unstable();
+        cells
+    }
+    pub fn dots(&self, width: u8) -> impl Iterator<Item = Dot> + '_ {
+        self.0.keys().map(move |&k| Dot::from_idx(k.into(), width))
+    }
+    /// Moves every dot with `f`, re-keying from a grid of `from` width to one
+    /// of `to` width. Values travel with their dots.
+    pub fn map_dots(&self, from: u8, to: u8, f: impl Fn(Dot) -> Dot) -> Self {
+        Self(
+            self.0
+                .iter()
+                .map(|(&k, &v)| (f(Dot::from_idx(k.into(), from)).to_idx(0, to) as Cell, v))
+                .collect(),
+            self.1,
+        )
+    }
+
+    pub fn contains_any(&self, ids: &[usize]) -> bool {
+        self.cells().any(|x| ids.contains(&x))
+    }
+    /// Top left and bottom right dots of the bounding box
+    pub fn bounds(&self, width: u8) -> (Dot, Dot) {
+        self.dots(width)
+            .fold((Dot(u8::MAX, u8::MAX), Dot(0, 0)), |(lo, hi), d| {
+                (
+                    Dot(lo.0.min(d.0), lo.1.min(d.1)),
+                    Dot(hi.0.max(d.0), hi.1.max(d.1)),
+                )
+            })
+    }
+    pub fn width(&self, width: u8) -> u8 {
+        let (lo, hi) = self.bounds(width);
+        hi.0 + 1 - lo.0
+    }
+    pub fn height(&self, width: u8) -> u8 {
+        let (lo, hi) = self.bounds(width);
+        hi.1 + 1 - lo.1
+    }
+    pub fn dim_in(&self, dir: Dir, width: u8) -> u8 {
+        dir.if_h(self.width(width), self.height(width))
+    }
+    /// The dots relative to the top left of the bounding box, sorted; two
+    /// bricks have the same shape when their footprints are equal
+    pub fn footprint(&self, width: u8) -> Vec<Dot> {
+        let (lo, _) = self.bounds(width);
+        let mut dots: Vec<_> = self
+            .dots(width)
+            .map(|d| Dot(d.0 - lo.0, d.1 - lo.1))
+            .collect();
+        dots.sort_unstable_by_key(|d| (d.1, d.0));
+        dots
+    }
+    /// The brick shifted so its bounding box starts at index 0
+    pub fn normalized(&self, width: u8) -> Self {
+        let (lo, _) = self.bounds(width);
+        self.map_dots(width, width, |d| Dot(d.0 - lo.0, d.1 - lo.1))
+    }
+    /// The brick turned a quarter clockwise for each turn `dir` is from `Up`,
+    /// within its bounding box, with `y` counting up: the same turns that
+    /// give a [`ShapeSet`](super::ShapeSet) shape its orientations. The top
+    /// left of the bounding box stays put; `None` if the turned brick runs
+    /// off the side of the grid.
+    pub fn rotated(&self, dir: Dir, width: u8) -> Option<Self> {
+        let (lo, hi) = self.bounds(width);
+        let (mut w, mut h) = (hi.0 + 1 - lo.0, hi.1 + 1 - lo.1);
+        let mut dots: Vec<_> = self
+            .0
+            .iter()
+            .map(|(&k, &v)| {
+                let d = Dot::from_idx(k.into(), width);
+                (Dot(d.0 - lo.0, d.1 - lo.1), v)
+            })
+            .collect();
+        for _ in 0..dir.quarter_turns() {
+            dots.iter_mut()
+                .for_each(|(d, _)| *d = Dot(d.1, w - 1 - d.0));
+            (w, h) = (h, w);
+        }
+        (lo.0 as usize + w as usize <= width as usize).then(|| {
+            Self(
+                dots.into_iter()
+                    .map(|(d, v)| (Dot(d.0 + lo.0, d.1 + lo.1).to_idx(0, width) as Cell, v))
+                    .collect(),
+                self.1,
+            )
+        })
+    }
+    /// Whether a dot of `other` is next to one of `self`, on the same grid
+    pub fn touches(&self, other: &Self, width: u8) -> bool {
+        self.dots(width).any(|x| {
+            other
+                .dots(width)
+                .any(|y| x.0.abs_diff(y.0) + x.1.abs_diff(y.1) == 1)
+        })
+    }
+
+    /// Tips the brick over the corner of whatever stops it moving towards
+    /// `dir`, a quarter turn, when the `blocked` cells (those with something
+    /// right ahead of them) are all to one side of its centre. `None` when
+    /// the contact is centred or the brick would tip off the grid; whether
+    /// the new cells are free is up to the caller.
+    pub fn tipped(&self, dir: Dir, blocked: &[usize], width: u8) -> Option<Self> {
+        // across and along the direction of travel
+        let fwd = dir.if_tr(1, -1);
+        let frame = |d: Dot| {
+            let (x, y) = (d.0 as i32, d.1 as i32);
+            dir.if_h((y, x * fwd), (x, y * fwd))
+        };
+        let (mut lo, mut hi) = (i32::MAX, i32::MIN);
+        for (p, _) in self.dots(width).map(frame) {
+            lo = lo.min(p);
+            hi = hi.max(p);
+        }
+        let contact: Vec<_> = blocked
+            .iter()
+            .map(|&k| frame(Dot::from_idx(k, width)))
+            .collect();
+        let (side, pivot) = match (contact.iter().min(), contact.iter().max()) {
+            (_, Some(&(p, m))) if 2 * p < lo + hi => (1, (p, m)),
+            (Some(&(p, _)), _) if 2 * p > lo + hi => {
+                let m = contact.iter().filter(|c| c.0 == p).map(|c| c.1).max()?;
+                (-1, (p, m))
+            }
+            _ => return None,
+        };
+        // where a cell lands, a quarter turn about the far corner of the pivot
+        let place = |d: Dot| {
+            let (p, m) = frame(d);
+            let (a, b) = (side * (p - pivot.0), m - pivot.1);
+            let (p, m) = (pivot.0 + side * (1 - b), (pivot.1 + a) * fwd);
+            dir.if_h((m, p), (p, m))
+        };
+        // the frame turns counterclockwise, which is clockwise on the grid
+        // when the frame is mirrored
+        let mirrored = (side * fwd < 0) != dir.is_horizontal();
+        let turn = if mirrored { Right } else { Left };
+        let (lo, hi) = self.bounds(width);
+        let ((x0, y0), (x1, y1)) = (place(lo), place(hi));
+        let (w, h) = (self.width(width), self.height(width));
+        let (x, y) = (x0.min(x1), y0.min(y1));
+        let last = (x + h as i32 - 1) + (y + w as i32 - 1) * width as i32;
+        if x + h as i32 > width as i32
+            || u8::try_from(y + w as i32 - 1).is_err()
+            || Cell::try_from(last).is_err()
+        {
+            return None;
+        }
+        let at = Dot(u8::try_from(x).ok()?, u8::try_from(y).ok()?);
+        // turned on a grid of its own, then put back at its new place
+        let span = w.max(h);
+        let turned = self
+            .map_dots(width, span, |d| Dot(d.0 - lo.0, d.1 - lo.1))
+            .rotated(turn, span)?;
+        Some(turned.map_dots(span, width, |d| Dot(d.0 + at.0, d.1 + at.1)))
+    }
+
+    /// Takes the `ids` cells out of the brick and splits what is left into
+    /// its connected pieces: `self` keeps one and its id, the rest are
+    /// returned as fragments numbered from `next`. Every piece keeps its
+    /// dots' values.
+    pub fn cut_at(&mut self, ids: &[usize], width: u8, next: &mut BrickId) -> Vec<Self> {
+        self.0.retain(|&k, _| !ids.contains(&(k as usize)));
+        // in cell order, so the same cut always yields the same fragments
+        let dots: Vec<_> = self
+            .sorted_cells()
+            .into_iter()
+            .map(|k| Dot::from_idx(k, width))
+            .collect();
+        let mut dot_groups: Vec<Self> = Dot::group_connected(&dots)
+            .iter()
+            .map(|g| {
+                let dots = g.iter().map(|d| {
+                    let k = d.to_idx(0, width) as Cell;
+                    (k, self.0[&k])
+                });
+                Self(dots.collect(), self.1)
+            })
+            .collect();
+        *self = dot_groups.pop().unwrap_or_default();
+        for b in &mut dot_groups {
+            b.1 = b.1.fragment(next.issue());
+        }
+        dot_groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shape_set::ShapeSet;
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg32;
+    use strum::IntoEnumIterator;
+
+    fn sets() -> [ShapeSet; 4] {
+        [
+            ShapeSet::classic(),
+            ShapeSet::polyominoes(3).unwrap(),
+            ShapeSet::polyominoes(4).unwrap(),
+            ShapeSet::polyominoes(5).unwrap(),
+        ]
+    }
+
+    /// Cells with their values, in order
+    fn valued(b: &Brick) -> Vec<(Cell, u8)> {
+        let mut ret: Vec<_> = b.0.iter().map(|(&k, &v)| (k, v)).collect();
+        ret.sort_unstable();
+        ret
+    }
+
+    #[test]
+    fn four_turns_return_the_brick() {
+        let width = 9;
+        for set in sets() {
+            for (shape, dir) in set.variants() {
+                for orig in [0, 2, 3 * width as usize + 1] {
+                    let mut brick = set.brick(shape, dir, orig, width).unwrap();
+                    for (v, k) in brick.sorted_cells().into_iter().enumerate() {
+                        brick.0.insert(k as Cell, v as u8);
+                    }
+                    let mut turned = brick.clone();
+                    for _ in 0..4 {
+                        turned = turned.rotated(Right, width).unwrap();
+                    }
+                    assert_eq!(valued(&turned), valued(&brick), "{shape:?} {dir:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn orientations_are_turns_of_the_base() {
+        let width = 9;
+        for set in sets() {
+            for (shape, dir) in set.variants() {
+                let base = set.brick(shape, Up, 0, width).unwrap();
+                let turned = base.rotated(dir, width).unwrap();
+                let brick = set.brick(shape, dir, 0, width).unwrap();
+                assert_eq!(turned.footprint(width), brick.footprint(width));
+                assert_eq!(brick.normalized(width).bounds(width).0, Dot(0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn turns_off_the_side_of_the_grid_are_refused() {
+        let width = 5;
+        let i = ShapeSet::classic().brick(Shape(2), Up, 3, width).unwrap();
+        assert!(i.rotated(Right, width).is_none());
+        assert!(i.rotated(Down, width).is_some());
+        assert_eq!(Dir::iter().map(|d| d.quarter_turns()).sum::<u8>(), 6);
+    }
+
+    #[test]
+    fn cuts_lose_and_duplicate_no_cell() {
+        let mut rng = Pcg32::seed_from_u64(24);
+        for _ in 0..2000 {
+            let (width, height) = (rng.gen_range(1..20u8), rng.gen_range(1..20u8));
+            let squares = width as u32 * height as u32;
+            let cells: HashMap<Cell, u8> = (0..rng.gen_range(0..60))
+                .map(|_| (rng.gen_range(0..squares) as Cell, rng.gen()))
+                .collect();
+            let brick = Brick(cells, Lineage::spawn(Some(Shape(1)), BrickId(7)));
+            let ids: Vec<usize> = brick.cells().filter(|_| rng.gen_ratio(1, 3)).collect();
+
+            let mut kept = brick.clone();
+            let mut next = BrickId(100);
+            let fragments = kept.cut_at(&ids, width, &mut next);
+
+            assert_eq!(kept.1, brick.1);
+            let mut seen = HashMap::new();
+            for (i, piece) in std::iter::once(&kept).chain(&fragments).enumerate() {
+                if i > 0 {
+                    assert_eq!(piece.1.parent, Some(brick.1.id));
+                    assert_eq!(piece.1.id, BrickId(99 + i as u32));
+                    assert_eq!((piece.1.shape, piece.1.root), (brick.1.shape, brick.1.root));
+                }
+                let dots: Vec<_> = piece.dots(width).collect();
+                assert_eq!(Dot::group_connected(&dots).len(), 1, "a piece is connected");
+                for (&k, &v) in &piece.0 {
+                    assert!(seen.insert(k, v).is_none(), "{k} is in two pieces");
+                }
+            }
+            let mut want = brick.0.clone();
+            want.retain(|&k, _| !ids.contains(&(k as usize)));
+            assert_eq!(seen, want);
+            assert_eq!(next.0, 100 + fragments.len() as u32);
+        }
+    }
+}
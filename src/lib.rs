@@ -11,17 +11,26 @@
     clippy::module_name_repetitions,
     clippy::redundant_pub_crate
 )]
-use bevy::log::LogSettings;
-use {avalanche::*, bevy::prelude::*, menu_plugin::MenuMaterials, std::time::Duration};
+#[cfg(feature = "app")]
+use {
+    avalanche::*,
+    bevy::{log::LogSettings, prelude::*},
+    menu_plugin::MenuMaterials,
+    std::time::Duration,
+};
 
+#[cfg(feature = "app")]
 mod avalanche;
+pub mod engine;
 
+#[cfg(feature = "app")]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 enum Game {
     Avalnche,
     Menu,
 }
 
+#[cfg(feature = "app")]
 #[bevy_main]
 pub fn main() {
     let mut app = App::new();
@@ -51,6 +60,7 @@ pub fn main() {
 }
 
 /// Pre launch setup of assets and options
+#[cfg(feature = "app")]
 fn startup(mut commands: Commands, mut menu: ResMut<MenuMaterials>, mut windows: ResMut<Windows>) {
     commands.spawn_bundle(Camera3dBundle::default());
     let window = windows.primary_mut();
@@ -61,6 +71,7 @@ fn startup(mut commands: Commands, mut menu: ResMut<MenuMaterials>, mut windows:
         .min(0.8 * window.requested_height());
     // menu.size = window.physical_width().min(window.physical_height()) as f32;
 }
+#[cfg(feature = "app")]
 fn game_timer(mut state: ResMut<State<Game>>, time: Res<Time>, mut timer: Local<Timer>) {
     if timer.duration() == Duration::ZERO {
         timer.set_duration(Duration::from_millis(9));
@@ -69,7 +80,7 @@ fn game_timer(mut state: ResMut<State<Game>>, time: Res<Time>, mut timer: Local<
         state.replace(Game::Avalnche).unwrap();
     }
 }
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "app", target_arch = "wasm32"))]
 fn handle_browser_resize(mut windows: ResMut<Windows>) {
     let window = windows.get_primary_mut().unwrap();
     let wasm_window = web_sys::window().unwrap();